@@ -1,4 +1,4 @@
-use ckydb::{self, Controller};
+use ckydb::{self, Controller, FsyncPolicy, Options};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 const RECORDS: [(&str, &str); 7] = [
@@ -29,11 +29,44 @@ fn setting_benchmark(c: &mut Criterion) {
     }
 }
 
+// Setting with each fsync policy
+fn fsync_benchmark(c: &mut Criterion) {
+    for fsync in [
+        FsyncPolicy::Never,
+        FsyncPolicy::OnEverySet,
+        FsyncPolicy::OnRoll,
+    ] {
+        let options = Options {
+            fsync,
+            ..Options::default()
+        };
+        let mut db = ckydb::connect_with_options("db", 4.0, 60.0, &options).unwrap();
+        c.bench_function(&format!("set with fsync {:?}", fsync), |b| {
+            b.iter(|| db.set(black_box("hey"), black_box("English")))
+        });
+    }
+}
+
+// Setting with and without buffering sets in memory
+fn flush_every_n_benchmark(c: &mut Criterion) {
+    for flush_every_n in [None, Some(100)] {
+        let options = Options {
+            flush_every_n,
+            ..Options::default()
+        };
+        let mut db = ckydb::connect_with_options("db", 4.0, 60.0, &options).unwrap();
+        c.bench_function(
+            &format!("set with flush_every_n {:?}", flush_every_n),
+            |b| b.iter(|| db.set(black_box("hey"), black_box("English"))),
+        );
+    }
+}
+
 // Updating
 fn updating_benchmark(c: &mut Criterion) {
     let mut db = ckydb::connect("db", 4.0, 60.0).unwrap();
     for (k, v) in RECORDS {
-        db.set(k, v).expect(&format!("set {}", k));
+        db.set(k, v).unwrap_or_else(|_| panic!("set {}", k));
     }
     for (k, v) in UPDATES {
         c.bench_function(&format!("update {} to {}", k, v), |b| {
@@ -46,7 +79,7 @@ fn updating_benchmark(c: &mut Criterion) {
 fn getting_benchmark(c: &mut Criterion) {
     let mut db = ckydb::connect("db", 4.0, 60.0).unwrap();
     for (k, v) in RECORDS {
-        db.set(k, v).expect(&format!("set {}", k));
+        db.set(k, v).unwrap_or_else(|_| panic!("set {}", k));
     }
     for (k, _) in RECORDS {
         c.bench_function(&format!("get {}", k), |b| b.iter(|| db.get(black_box(k))));
@@ -57,7 +90,7 @@ fn getting_benchmark(c: &mut Criterion) {
 fn deleting_benchmark(c: &mut Criterion) {
     let mut db = ckydb::connect("db", 4.0, 60.0).unwrap();
     for (k, v) in RECORDS {
-        db.set(k, v).expect(&format!("set {}", k));
+        db.set(k, v).unwrap_or_else(|_| panic!("set {}", k));
     }
 
     for (k, _) in RECORDS {
@@ -71,18 +104,40 @@ fn deleting_benchmark(c: &mut Criterion) {
 fn clearing_benchmark(c: &mut Criterion) {
     let mut db = ckydb::connect("db", 4.0, 60.0).unwrap();
     for (k, v) in RECORDS {
-        db.set(k, v).expect(&format!("set {}", k));
+        db.set(k, v).unwrap_or_else(|_| panic!("set {}", k));
     }
 
     c.bench_function("clear", |b| b.iter(|| db.clear()));
 }
 
+// Vacuuming many data files on reload
+fn vacuuming_benchmark(c: &mut Criterion) {
+    // a tiny max_file_size_kb rolls the log file on every set
+    let mut db = ckydb::connect("db", 0.01, 60.0).unwrap();
+    db.clear().expect("clear db");
+    for i in 0..200 {
+        db.set(&format!("key-{}", i), "value").expect("set key");
+    }
+
+    c.bench_function("vacuum 200 data files", |b| {
+        b.iter(|| {
+            db.set(black_box("hey"), black_box("English"))
+                .expect("set hey");
+            db.delete(black_box("hey")).expect("delete hey");
+            db.reload()
+        })
+    });
+}
+
 criterion_group!(
     benches,
     setting_benchmark,
+    fsync_benchmark,
+    flush_every_n_benchmark,
     updating_benchmark,
     getting_benchmark,
     deleting_benchmark,
-    clearing_benchmark
+    clearing_benchmark,
+    vacuuming_benchmark
 );
 criterion_main!(benches);
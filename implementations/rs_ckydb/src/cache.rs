@@ -1,3 +1,4 @@
+use crate::utils;
 use std::collections::HashMap;
 
 /// `Caching` trait gives the basic representation of what
@@ -50,22 +51,40 @@ impl Cache {
         }
     }
 
-    /// Initializes a new empty Cache with start: "0", end: "0" and data as empty Hashmap
+    /// Initializes a new empty Cache with empty `start` and `end` bounds and data as empty Hashmap.
+    /// The empty bounds mark the cache as empty so that no key is ever in its range
     // #[inline]
     pub(crate) fn new_empty() -> Cache {
         Cache {
             data: Default::default(),
-            start: "0".to_string(),
-            end: "0".to_string(),
+            start: "".to_string(),
+            end: "".to_string(),
         }
     }
+
+    /// Returns the approximate memory taken up by the keys and values in the cache in kilobytes
+    // #[inline]
+    pub(crate) fn get_size_kb(&self) -> f64 {
+        let size_in_bytes: usize = self.data.iter().map(|(k, v)| k.len() + v.len()).sum();
+        size_in_bytes as f64 / 1024.0
+    }
+
+    /// Checks whether this cache was initialized as an empty cache i.e. it holds no data file
+    // #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.start.is_empty() && self.end.is_empty()
+    }
 }
 
 impl Caching for Cache {
     // #[inline]
     fn is_in_range(&self, key: &str) -> bool {
-        let key = key.to_string();
-        self.start <= key && key <= self.end
+        if self.is_empty() {
+            return false;
+        }
+
+        utils::compare_timestamps(&self.start, key).is_le()
+            && utils::compare_timestamps(key, &self.end).is_le()
     }
 
     // #[inline]
@@ -83,3 +102,30 @@ impl Caching for Cache {
         self.data.get(key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::{Cache, Caching};
+
+    #[test]
+    fn empty_cache_has_no_key_in_range() {
+        let cache = Cache::new_empty();
+
+        for key in ["", "0", "00", "-", "1-a", "1655375120328185000-cow"] {
+            assert!(!cache.is_in_range(key), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn cache_has_keys_between_its_bounds_in_range() {
+        let cache = Cache::new(
+            Default::default(),
+            "1655375120328185000",
+            "1655375120328186000",
+        );
+
+        assert!(cache.is_in_range("1655375120328185000-cow"));
+        assert!(!cache.is_in_range("0"));
+        assert!(!cache.is_in_range("1655375120328186000-cow"));
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `Clock` trait represents the source of the timestamps used for naming log files and for
+/// creating timestamped keys
+///
+/// It should be able to tell the time [now] in nanoseconds since the UNIX epoch
+///
+/// [now]: Clock::now
+pub(crate) trait Clock {
+    /// Returns the current timestamp in nanoseconds since the UNIX epoch
+    ///
+    /// # Errors
+    /// - [io::Error] in case the current time cannot be determined
+    ///
+    /// [io::Error]: std::io::Error
+    fn now(&self) -> io::Result<u128>;
+}
+
+/// `SystemClock` is the real clock that reads the time from the system
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    // #[inline]
+    fn now(&self) -> io::Result<u128> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .map_err(io::Error::other)
+    }
+}
+
+/// `MockClock` is a clock for tests that starts at a given timestamp and advances by
+/// a given `step` each time it is read. A `step` of zero gives a frozen clock.
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct MockClock {
+    next: std::cell::Cell<u128>,
+    step: u128,
+}
+
+#[cfg(test)]
+impl MockClock {
+    /// Initializes a new MockClock that first returns `start` and then advances by `step`
+    pub(crate) fn new(start: u128, step: u128) -> MockClock {
+        MockClock {
+            next: std::cell::Cell::new(start),
+            step,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> io::Result<u128> {
+        let now = self.next.get();
+        self.next.set(now + self.step);
+        Ok(now)
+    }
+}
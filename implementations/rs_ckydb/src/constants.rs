@@ -4,5 +4,9 @@ pub(crate) const TOKEN_SEPARATOR: &str = "$%#@*&^&";
 pub(crate) const LOG_FILE_EXT: &str = "log";
 pub(crate) const DATA_FILE_EXT: &str = "cky";
 
+/// The number of digits to which timestamps are zero-padded so that their lexical order is
+/// their chronological order. Nanoseconds since the UNIX epoch fit in 20 digits till the year 2554
+pub(crate) const TIMESTAMP_WIDTH: usize = 20;
+
 pub(crate) const INDEX_FILENAME: &str = "index.idx";
 pub(crate) const DEL_FILENAME: &str = "delete.del";
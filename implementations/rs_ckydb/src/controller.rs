@@ -1,6 +1,16 @@
-use crate::errors::{CorruptedDataError, NotFoundError};
-use crate::store::{Storage, Store};
+use crate::errors::{
+    AlreadyRunningError, InvalidVacuumIntervalError, NotADatabaseError, NotAnIntegerError,
+    NotFoundError, NotRunningError, SymlinkedDbPathError, VerificationError,
+};
+use crate::options::{DbConfig, Options};
+use crate::snapshot::Snapshot;
+use crate::store::{
+    KeyLocationCounts, KeyStatus, MergePolicy, Stats, Storage, Store, VerifyReport,
+};
+use std::collections::HashMap;
 use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
@@ -13,7 +23,8 @@ use std::{io, thread};
 ///
 /// It must also be able to [open] the connection to the underlying database, so as to start
 /// some house-cleaning background tasks. It should also [close] the connection, and stop
-/// the background tasks
+/// the background tasks. It should also be able to [reload] the data from disk to pick up
+/// changes made by other processes
 ///
 /// [set]: Controller::set
 /// [get]: Controller::get
@@ -21,32 +32,100 @@ use std::{io, thread};
 /// [clear]: Controller::clear
 /// [open]: Controller::open
 /// [close]: Controller::close
+/// [reload]: Controller::reload
 pub trait Controller {
     /// Loads the store and starts the background tasks
     ///
     /// # Errors
+    /// - [AlreadyRunningError] wrapped in an [io::Error] in case the database is already open
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
     /// is not accessible
     ///
     /// [io::Error]: std::io::Error
+    /// [AlreadyRunningError]: crate::errors::AlreadyRunningError
     fn open(&mut self) -> io::Result<()>;
 
-    /// Stops all background tasks
+    /// Returns the configuration with which the database was connected to
+    fn config(&self) -> &DbConfig;
+
+    /// Returns the path to the database folder
+    fn db_path(&self) -> &Path;
+
+    /// Writes any buffered sets to disk and stops all background tasks
     ///
     /// # Errors
+    /// - [NotRunningError] wrapped in an [io::Error] in case the database is already closed
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
     /// is not accessible
     ///
     /// [io::Error]: std::io::Error
+    /// [NotRunningError]: crate::errors::NotRunningError
     fn close(&mut self) -> io::Result<()>;
 
     /// Adds or updates the value corresponding to the given key in store
     ///
     /// # Errors
-    /// - [CorruptedDataError] in case the data on disk is inconsistent with that in memory
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case the key is empty
+    /// - [ValueTooLargeError] wrapped in an [io::Error] in case the value is bigger than the
+    ///   maximum value size
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
     ///
+    /// [io::Error]: std::io::Error
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    /// [ValueTooLargeError]: crate::errors::ValueTooLargeError
     /// [CorruptedDataError]: crate::errors::CorruptedDataError
-    fn set(&mut self, key: &str, value: &str) -> Result<(), CorruptedDataError>;
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()>;
+
+    /// Adds the given key and value to the store only if the key is not already in it.
+    /// Unlike [Controller::set], it never overwrites an existing value, which makes it suitable
+    /// for allocating unique ids
+    ///
+    /// # Errors
+    /// - [AlreadyExistsError] wrapped in an [io::Error] in case the key is already in the store
+    /// - See [Controller::set] for the errors that can occur on saving the key-value pair
+    ///
+    /// [io::Error]: std::io::Error
+    /// [AlreadyExistsError]: crate::errors::AlreadyExistsError
+    fn insert_new(&mut self, key: &str, value: &str) -> io::Result<()>;
+
+    /// Adds or updates the values corresponding to the keys of all the pairs yielded by `pairs`
+    /// in one batch, returning the number of pairs written.
+    /// This avoids rewriting the log file for every pair, and saves callers from collecting
+    /// the pairs into a collection first.
+    ///
+    /// # Errors
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case any key is empty. The pairs before it
+    ///   are still written
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    fn set_many_from_iter<I: IntoIterator<Item = (String, String)>>(
+        &mut self,
+        pairs: I,
+    ) -> io::Result<usize>;
+
+    /// Writes any sets buffered in memory to disk. Sets are only buffered if the database
+    /// was connected with [Options::flush_every_n] set
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Moves all recently set key-value pairs from memory, i.e. the memtable, into a new data file
+    /// on disk, whatever the size of the current log file. This is useful before taking a backup
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn checkpoint(&mut self) -> io::Result<()>;
 
     /// Retrieves the value corresponding to the given key
     ///
@@ -56,15 +135,351 @@ pub trait Controller {
     /// [NotFoundError]: crate::errors::NotFoundError
     fn get(&mut self, key: &str) -> Result<String, NotFoundError>;
 
-    /// Removes the key-value pair corresponding to the passed key
+    /// Retrieves the value corresponding to the given key, or `None` if the key is not found.
+    /// Unlike [Controller::get], a missing key is not treated as an error
     ///
     /// # Errors
-    /// - [NotFoundError] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn try_get(&mut self, key: &str) -> io::Result<Option<String>>;
+
+    /// Retrieves the time, in nanoseconds since the UNIX epoch, at which the value of the given key
+    /// was last set. This is only recorded if the database was connected with
+    /// [Options::track_last_modified] on
+    ///
+    /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [NotTrackedError] wrapped in an [io::Error] in case the key was last set without
+    ///   [Options::track_last_modified] on
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [NotTrackedError]: crate::errors::NotTrackedError
+    fn last_modified(&mut self, key: &str) -> io::Result<u64>;
+
+    /// Retrieves the values corresponding to the given keys as a map of key to value.
+    /// Keys that are not found, or whose values cannot be read e.g. because their data file
+    /// is missing, are left out of the map.
+    fn get_map(&mut self, keys: &[&str]) -> HashMap<String, String>;
+
+    /// Retrieves the values corresponding to the given keys, returning a result for each key
+    /// in the same order as `keys`. The values are however fetched in the order in which
+    /// their keys were created, so that each data file is loaded from disk at most once
+    ///
+    /// # Errors
+    /// Each result is one of:
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn multi_get(&mut self, keys: &[&str]) -> Vec<io::Result<String>>;
+
+    /// Captures a read-only view of the database as it is at this moment, that can be read
+    /// from while writes to the database proceed. See [Snapshot] for the isolation it offers
+    ///
+    /// [Snapshot]: crate::snapshot::Snapshot
+    fn snapshot(&mut self) -> Snapshot;
+
+    /// Warms the cache with the data file holding the timestamps from `start` up to `end`,
+    /// so that a known burst of reads of the keys created in that time is served from memory
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn preload_range(&mut self, start: &str, end: &str) -> io::Result<()>;
+
+    /// Checks whether the most recent value read was served from memory, rather than requiring
+    /// a data file to be loaded from disk. This helps in tuning `max_file_size_kb` empirically
+    fn last_read_was_cached(&self) -> bool;
+
+    /// Checks whether the database is in a read-only degraded mode, entered when its index file
+    /// or del file could not be written e.g. because the database folder became read-only.
+    /// Writes are then rejected with a [DegradedError] wrapped in an [io::Error], while
+    /// reads of existing keys still work. Connecting again clears it
+    ///
+    /// [io::Error]: std::io::Error
+    /// [DegradedError]: crate::errors::DegradedError
+    fn is_degraded(&self) -> bool;
+
+    /// Adds `delta` to the integer value corresponding to the given key, saving and returning
+    /// the new value. If the key does not exist, its value is taken to be 0.
+    /// A negative `delta` decrements the value.
+    ///
+    /// # Errors
+    /// - [NotAnIntegerError] wrapped in an [io::Error] in case the current value is not an integer
+    ///   or the new value does not fit in 64 bits
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - See [Controller::set] for the errors that can occur on saving the new value
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotAnIntegerError]: crate::errors::NotAnIntegerError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn increment(&mut self, key: &str, delta: i64) -> io::Result<i64>;
+
+    /// Appends `suffix` to the value corresponding to the given key, saving and returning
+    /// the new value. If the key does not exist, its value is taken to be an empty string.
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - See [Controller::set] for the errors that can occur on saving the new value
     ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn append(&mut self, key: &str, suffix: &str) -> io::Result<String>;
+
+    /// Retrieves the value corresponding to the given key or, if the key does not exist,
+    /// saves `default` as its value and returns it. This is useful for lazily initializing
+    /// settings that should then stay the same
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - See [Controller::set] for the errors that can occur on saving `default`
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn get_or_set(&mut self, key: &str, default: &str) -> io::Result<String>;
+
+    /// Reports whether the given key is [present], [deleted] but not yet vacuumed, or [absent]
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    /// [present]: crate::store::KeyStatus::Present
+    /// [deleted]: crate::store::KeyStatus::Deleted
+    /// [absent]: crate::store::KeyStatus::Absent
+    fn status(&mut self, key: &str) -> io::Result<KeyStatus>;
+
+    /// Removes the key-value pair corresponding to the passed key, returning the value removed
+    ///
+    /// # Errors
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case the key is empty
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn delete(&mut self, key: &str) -> io::Result<String>;
+
+    /// Removes the key-value pair corresponding to the passed key only if `predicate` returns true
+    /// for its current value, returning whether it was removed. A missing key is not removed.
+    /// This is useful for conditional cleanups e.g. deleting a key only if its value is "expired"
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn delete_if<F: Fn(&str) -> bool>(&mut self, key: &str, predicate: F) -> io::Result<bool>;
+
+    /// Restores the key-value pair of a key that was deleted, as long as the deletion has not yet
+    /// been vacuumed. This gives a short window in which accidental deletes can be recovered
+    ///
+    /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key was not deleted
+    ///   or the deletion was already vacuumed
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    fn undelete(&mut self, key: &str) -> io::Result<()>;
+
+    /// Renames the given `key` to `new_key`, keeping its value.
+    /// Any value that `new_key` had is overwritten.
+    ///
+    /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - See [Controller::set] for the errors that can occur on saving the value under `new_key`
+    ///
+    /// [io::Error]: std::io::Error
     /// [NotFoundError]: crate::errors::NotFoundError
-    fn delete(&mut self, key: &str) -> Result<(), NotFoundError>;
+    fn rename(&mut self, key: &str, new_key: &str) -> io::Result<()>;
+
+    /// Moves the value of the given `key` from its data file into the memtable, without changing
+    /// it, so that later reads of a hot old key do not need its data file to be loaded.
+    /// Keys already in the memtable are left as they are
+    ///
+    /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - See [Controller::set] for the errors that can occur on saving the value afresh
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn touch(&mut self, key: &str) -> io::Result<()>;
+
+    /// Removes all key-value pairs whose keys start with the given `prefix`
+    /// e.g. "session:" to remove all "session:*" keys, returning the number of keys removed
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn clear_prefix(&mut self, prefix: &str) -> io::Result<usize>;
+
+    /// Retrieves all the key-value pairs for which `predicate(key, value)` is true,
+    /// in the order in which the keys were created
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn filter<F: Fn(&str, &str) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> io::Result<Vec<(String, String)>>;
+
+    /// Retrieves all the key-value pairs whose keys match the glob `pattern`,
+    /// in the order in which the keys were created. In the pattern, `*` matches any sequence
+    /// of characters, `?` matches exactly one character and `\` escapes the next character
+    /// e.g. `user:*:active` or `price\*` for keys with a literal `*`
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn scan_glob(&mut self, pattern: &str) -> io::Result<Vec<(String, String)>>;
 
-    /// Resets the entire Store, and clears everything on disk
+    /// Checks whether any key in the database has the given `value`.
+    /// This scans all key-value pairs since there is no index of values, so it is best
+    /// suited to small databases and tests
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn contains_value(&mut self, value: &str) -> io::Result<bool>;
+
+    /// Cross-checks the index against the log file, the data files and the del file, reporting
+    /// every inconsistency found. This is the tool to run when data corruption is suspected.
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn verify(&mut self) -> io::Result<VerifyReport>;
+
+    /// Releases the memory held in excess by the database's in-memory structures,
+    /// which keep their allocated capacity after keys are deleted.
+    /// It is best called after deleting a large batch of keys
+    fn shrink_memory(&mut self);
+
+    /// Counts how many live keys are read from memory i.e. the memtable, and how many from
+    /// the data files on disk. Many keys in the memtable may mean `max_file_size_kb` is too big,
+    /// and many keys in the data files mean more reads have to load data files into the cache
+    fn keys_count_by_location(&self) -> KeyLocationCounts;
+
+    /// Counts the keys that have been deleted but whose values are still on disk, awaiting the
+    /// next vacuum. A big count may mean the vacuum interval is too long
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn pending_deletes(&self) -> io::Result<usize>;
+
+    /// Returns the total size in bytes of all the database's files on disk,
+    /// which is useful for capacity planning
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn size_on_disk(&self) -> io::Result<u64>;
+
+    /// Returns, for every data file and log file, the ratio of its bytes taken up by deleted
+    /// key-value pairs that are yet to be vacuumed, sorted by file name.
+    /// This helps decide whether, and which files, to vacuum
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible, or of kind [io::ErrorKind::InvalidData] in case a file is malformed
+    ///
+    /// [io::Error]: std::io::Error
+    fn fragmentation(&self) -> io::Result<Vec<(String, f64)>>;
+
+    /// Gathers the metrics of the database i.e. the number of keys and data files,
+    /// the size on disk, the number of deletes yet to be vacuumed and the cache hits and misses
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn stats(&self) -> io::Result<Stats>;
+
+    /// Gathers the [stats] of the database as a JSON object, ready to be logged or exposed
+    /// on an HTTP endpoint for monitoring
+    ///
+    /// # Errors
+    /// - See [Controller::stats]
+    ///
+    /// [stats]: Controller::stats
+    fn stats_json(&self) -> io::Result<String>;
+
+    /// Returns the key-value pairs written recently i.e. those still in the memtable,
+    /// in the order in which they were created. No data files are read
+    fn recent_entries(&self) -> Vec<(String, String)>;
+
+    /// Returns the timestamped keys and values saved in the data file `filename`
+    /// e.g. "01655375120328185000.cky", in the order in which they were created.
+    /// This is useful for tooling that inspects data files one at a time, as the values
+    /// cached for reads are left as they are
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the data file
+    ///   is not accessible, or of kind [io::ErrorKind::InvalidData] in case it is malformed
+    ///
+    /// [io::Error]: std::io::Error
+    fn iter_data_file(&self, filename: &str) -> io::Result<Vec<(String, String)>>;
+
+    /// Sets all the key-value pairs of the ckydb database at `other_db_path` into this database,
+    /// returning the number of pairs written. This is useful for combining sharded datasets.
+    /// For keys found in both databases, the `policy` decides which value is kept.
+    /// The other database is left unchanged
+    ///
+    /// # Errors
+    /// - [NotADatabaseError] wrapped in an [io::Error] if `other_db_path` is not
+    ///   an existing ckydb database
+    /// - See [Controller::set_many_from_iter] for the errors that can occur on saving the pairs
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotADatabaseError]: crate::errors::NotADatabaseError
+    fn merge_from(&mut self, other_db_path: &str, policy: MergePolicy) -> io::Result<usize>;
+
+    /// Resets the entire Store, and clears everything on disk.
+    /// The background vacuum task waits for the clearing to finish before touching any files
     ///
     /// # Errors
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
@@ -72,14 +487,43 @@ pub trait Controller {
     ///
     /// [io::Error]: std::io::Error
     fn clear(&mut self) -> io::Result<()>;
+
+    /// Clears the database almost instantly by swapping its folder for a fresh empty one,
+    /// and deleting the old folder in a background task whose handle is returned.
+    /// Unlike [Controller::clear], the caller does not wait for all the files to be deleted
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn clear_async(&mut self) -> io::Result<JoinHandle<io::Result<()>>>;
+
+    /// Reloads the store from the data on disk without clearing anything on disk
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn reload(&mut self) -> io::Result<()>;
 }
 
 /// `Ckydb` is the public API for the database.
 /// It implements the [Controller] trait as well as the [Drop] trait
+///
+/// It is loaded and [opened] on [connecting], which starts its background vacuum task.
+/// It can be [closed] to stop the task, and [opened] again to reload the store from disk
+/// and restart the task. Opening it while open, or closing it while closed, is an error.
+/// It is closed automatically when dropped, if it is still open.
+///
+/// [opened]: Controller::open
+/// [closed]: Controller::close
+/// [connecting]: crate::controller::connect
 pub struct Ckydb {
     tasks: Option<Vec<JoinHandle<()>>>,
-    store: Arc<Mutex<Store>>,
-    vacuum_interval_sec: f64,
+    store: Arc<Mutex<Box<dyn Storage + Send>>>,
+    config: DbConfig,
     is_open: bool,
     tx: mpsc::Sender<Signal>,
     rv: Arc<Mutex<mpsc::Receiver<Signal>>>,
@@ -91,16 +535,97 @@ impl Ckydb {
     /// # Errors
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the `db_path` database folder
     /// is not accessible
+    /// - [NotADatabaseError] wrapped in an [io::Error] if `options.from_existing` is set yet the
+    ///   `db_path` folder is not an existing ckydb database
+    /// - [SymlinkedDbPathError] wrapped in an [io::Error] if `options.refuse_symlinked_db_path`
+    ///   is set yet the `db_path` folder is a symbolic link
+    /// - [InvalidVacuumIntervalError] wrapped in an [io::Error] if `vacuum_interval_sec` is
+    ///   not a positive number of seconds
+    /// - [VerificationError] wrapped in an [io::Error] if `options.verify_on_connect` is set
+    ///   and the database is inconsistent
+    /// - [WrongEncryptionKeyError] wrapped in an [io::Error] if `options.encryption_key` is set
+    ///   yet the saved values cannot be decrypted with it
     ///
     /// [io::Error]: std::io::Error
-    fn new(db_path: &str, max_file_size_kb: f64, vacuum_interval_sec: f64) -> io::Result<Ckydb> {
+    /// [NotADatabaseError]: crate::errors::NotADatabaseError
+    /// [SymlinkedDbPathError]: crate::errors::SymlinkedDbPathError
+    /// [InvalidVacuumIntervalError]: crate::errors::InvalidVacuumIntervalError
+    /// [VerificationError]: crate::errors::VerificationError
+    /// [WrongEncryptionKeyError]: crate::errors::WrongEncryptionKeyError
+    fn new(
+        db_path: &Path,
+        max_file_size_kb: f64,
+        vacuum_interval_sec: f64,
+        options: &Options,
+    ) -> io::Result<Ckydb> {
         let mut store = Store::new(db_path, max_file_size_kb);
+        store.set_vacuum_on_load(options.vacuum_on_load);
+        store.set_in_memory(options.in_memory);
+        store.set_fsync(options.fsync);
+        store.set_max_value_size_bytes(options.max_value_size_bytes);
+        store.set_split_data_files(options.split_data_files);
+        store.set_resilient_reads(options.resilient_reads);
+        store.set_cache_memory_budget_kb(options.cache_memory_budget_kb);
+        store.set_max_log_entries(options.max_log_entries);
+        store.set_roll_hook(options.roll_hook.clone());
+        store.set_load_progress_hook(options.load_progress_hook.clone());
+        store.set_min_data_file_kb(options.min_data_file_kb);
+        store.set_track_last_modified(options.track_last_modified);
+        store.set_retry_policy(options.retry_policy);
+        #[cfg(feature = "encryption")]
+        store.set_encryption_key(options.encryption_key);
+        store.set_flush_every_n(options.flush_every_n);
+
+        if options.from_existing && !store.exists_on_disk() {
+            return Err(NotADatabaseError.into());
+        }
+
+        if options.refuse_symlinked_db_path && store.is_db_path_symlink() {
+            return Err(SymlinkedDbPathError.into());
+        }
+
+        let config = DbConfig::new(db_path, max_file_size_kb, vacuum_interval_sec, options);
+        let db = Ckydb::with_storage(Box::new(store), config)?;
+
+        if options.verify_on_connect {
+            let report = db.store.lock().expect("set store").verify()?;
+            if let Some(inconsistency) = report.inconsistencies.into_iter().next() {
+                return Err(VerificationError { inconsistency }.into());
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Creates a new instance of Ckydb over the given `storage`, loading it.
+    /// This allows the database to run over backends other than the file-based store
+    /// e.g. a remote key-value service or a mock in tests. See [Storage] for what a backend
+    /// must implement. The vacuum interval in the `config` is used by the background vacuuming
+    /// task, while its other settings are only reported back by [Controller::config]
+    ///
+    /// # Errors
+    /// - [InvalidVacuumIntervalError] wrapped in an [io::Error] if `config.vacuum_interval_sec`
+    ///   is not a positive number of seconds
+    /// - See [Storage::load] for the errors that can occur on loading the `storage`
+    ///
+    /// [io::Error]: std::io::Error
+    /// [InvalidVacuumIntervalError]: crate::errors::InvalidVacuumIntervalError
+    pub fn with_storage(
+        mut storage: Box<dyn Storage + Send>,
+        config: DbConfig,
+    ) -> io::Result<Ckydb> {
+        let vacuum_interval_sec = config.vacuum_interval_sec;
+        if !(vacuum_interval_sec > 0.0 && Duration::try_from_secs_f64(vacuum_interval_sec).is_ok())
+        {
+            return Err(InvalidVacuumIntervalError.into());
+        }
+
         let (tx, rv) = mpsc::channel();
 
-        store.load().and(Ok(Ckydb {
+        storage.load().and(Ok(Ckydb {
             tasks: Some(vec![]),
-            store: Arc::new(Mutex::new(store)),
-            vacuum_interval_sec,
+            store: Arc::new(Mutex::new(storage)),
+            config,
             is_open: false,
             tx,
             rv: Arc::new(Mutex::new(rv)),
@@ -111,41 +636,37 @@ impl Ckydb {
 impl Controller for Ckydb {
     fn open(&mut self) -> io::Result<()> {
         if self.is_open {
-            return Ok(());
+            return Err(AlreadyRunningError.into());
+        }
+
+        // the tasks are only taken away on close, so the store was loaded on creation
+        // and needs reloading only when reopening after a close
+        let is_reopening = self.tasks.is_none();
+        if is_reopening {
+            self.store.lock().expect("set store").reload()?;
         }
 
         let store = Arc::clone(&self.store);
-        let vacuum_interval_sec = self.vacuum_interval_sec;
+        let vacuum_interval_sec = self.config.vacuum_interval_sec;
         let rv = Arc::clone(&self.rv);
 
         let vacuum_task = thread::spawn(move || {
             let interval = Duration::from_secs_f64(vacuum_interval_sec);
-            let wait_interval_as_millis = 100;
-            let number_of_waits = interval.as_millis() / wait_interval_as_millis;
-            let wait_interval = Duration::from_millis(wait_interval_as_millis as u64);
-            let mut wait = 0 as u128;
 
             loop {
-                let rv = rv.lock().expect("get rv lock");
-                let signal = rv.try_recv().unwrap_or(Signal::Continue);
+                // wait for the interval, unless a stop signal is sent in the meantime
+                let signal = rv.lock().expect("get rv lock").recv_timeout(interval);
 
                 match signal {
-                    Signal::Stop => break,
-                    Signal::Continue => {
-                        if wait < number_of_waits {
-                            thread::sleep(wait_interval);
-                        } else {
-                            if let Ok(store) = store.lock() {
-                                store
-                                    .vacuum()
-                                    .unwrap_or_else(|err| println!("vacuum error: {}", err));
-                            }
-                            wait = 0;
+                    Ok(Signal::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Ok(mut store) = store.lock() {
+                            store
+                                .vacuum()
+                                .unwrap_or_else(|err| println!("vacuum error: {}", err));
                         }
                     }
                 }
-
-                wait += 1;
             }
         });
 
@@ -155,20 +676,29 @@ impl Controller for Ckydb {
         Ok(())
     }
 
+    fn config(&self) -> &DbConfig {
+        &self.config
+    }
+
+    fn db_path(&self) -> &Path {
+        &self.config.db_path
+    }
+
     fn close(&mut self) -> io::Result<()> {
         if !self.is_open {
-            return Ok(());
+            return Err(NotRunningError.into());
         }
 
+        self.flush()?;
+
         if let Some(tasks) = self.tasks.take() {
             for task in tasks {
                 self.tx
                     .send(Signal::Stop)
                     .or_else(|err| Err(io::Error::new(ErrorKind::Other, err)))?;
 
-                while !task.is_finished() {
-                    thread::sleep(Duration::from_millis(100));
-                }
+                task.join()
+                    .or(Err(io::Error::other("background task panicked")))?;
             }
         }
 
@@ -176,68 +706,328 @@ impl Controller for Ckydb {
         Ok(())
     }
 
-    fn set(&mut self, key: &str, value: &str) -> Result<(), CorruptedDataError> {
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.store.lock().expect("set store").set(key, value)
+    }
+
+    fn insert_new(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.store.lock().expect("set store").insert_new(key, value)
+    }
+
+    fn set_many_from_iter<I: IntoIterator<Item = (String, String)>>(
+        &mut self,
+        pairs: I,
+    ) -> io::Result<usize> {
         self.store
             .lock()
-            .and_then(|mut store| Ok(store.set(key, value)))
             .expect("set store")
+            .set_many_from_iter(&mut pairs.into_iter())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.store.lock().expect("set store").flush()
+    }
+
+    fn checkpoint(&mut self) -> io::Result<()> {
+        self.store.lock().expect("set store").checkpoint()
     }
 
     fn get(&mut self, key: &str) -> Result<String, NotFoundError> {
-        self.store
-            .lock()
-            .and_then(|mut store| Ok(store.get(key)))
-            .expect("set store")
+        self.store.lock().expect("set store").get(key)
     }
 
-    fn delete(&mut self, key: &str) -> Result<(), NotFoundError> {
-        self.store
-            .lock()
-            .and_then(|mut store| Ok(store.delete(key)))
-            .expect("set store")
+    fn try_get(&mut self, key: &str) -> io::Result<Option<String>> {
+        self.store.lock().expect("set store").try_get(key)
     }
 
-    fn clear(&mut self) -> io::Result<()> {
+    fn last_modified(&mut self, key: &str) -> io::Result<u64> {
+        self.store.lock().expect("set store").last_modified(key)
+    }
+
+    fn get_map(&mut self, keys: &[&str]) -> HashMap<String, String> {
+        self.store.lock().expect("set store").get_map(keys)
+    }
+
+    fn multi_get(&mut self, keys: &[&str]) -> Vec<io::Result<String>> {
+        self.store.lock().expect("set store").multi_get(keys)
+    }
+
+    fn snapshot(&mut self) -> Snapshot {
+        self.store.lock().expect("set store").snapshot()
+    }
+
+    fn preload_range(&mut self, start: &str, end: &str) -> io::Result<()> {
         self.store
             .lock()
-            .and_then(|mut store| Ok(store.clear()))
             .expect("set store")
+            .preload_range(start, end)
     }
-}
 
-impl Drop for Ckydb {
-    fn drop(&mut self) {
-        self.close().unwrap_or(());
+    fn last_read_was_cached(&self) -> bool {
+        self.store.lock().expect("set store").last_read_was_cached()
     }
-}
 
-/// Connects to the Ckydb instance, initializing it with its background tasks and returns it.
-/// `max_file_size_kb` is the maximum file size permitted for the database files. Make sure it fits in RAM.
-/// `vacuum_interval_sec` is the time between [vacuuming] cycles for the database.
-///
+    fn is_degraded(&self) -> bool {
+        self.store.lock().expect("set store").is_degraded()
+    }
+
+    fn increment(&mut self, key: &str, delta: i64) -> io::Result<i64> {
+        let mut store = self.store.lock().expect("set store");
+        let current_value = match store.try_get(key)? {
+            Some(value) => value.parse::<i64>().or(Err(NotAnIntegerError))?,
+            None => 0,
+        };
+        let new_value = current_value.checked_add(delta).ok_or(NotAnIntegerError)?;
+
+        store.set(key, &new_value.to_string())?;
+
+        Ok(new_value)
+    }
+
+    fn append(&mut self, key: &str, suffix: &str) -> io::Result<String> {
+        let mut store = self.store.lock().expect("set store");
+        let new_value = store.try_get(key)?.unwrap_or_default() + suffix;
+
+        store.set(key, &new_value)?;
+
+        Ok(new_value)
+    }
+
+    fn get_or_set(&mut self, key: &str, default: &str) -> io::Result<String> {
+        let mut store = self.store.lock().expect("set store");
+        if let Some(value) = store.try_get(key)? {
+            return Ok(value);
+        }
+
+        store.set(key, default)?;
+
+        Ok(default.to_string())
+    }
+
+    fn status(&mut self, key: &str) -> io::Result<KeyStatus> {
+        self.store.lock().expect("set store").status(key)
+    }
+
+    fn delete(&mut self, key: &str) -> io::Result<String> {
+        self.store.lock().expect("set store").delete(key)
+    }
+
+    fn delete_if<F: Fn(&str) -> bool>(&mut self, key: &str, predicate: F) -> io::Result<bool> {
+        let mut store = self.store.lock().expect("set store");
+        match store.try_get(key)? {
+            Some(value) if predicate(&value) => {
+                store.delete(key)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn undelete(&mut self, key: &str) -> io::Result<()> {
+        self.store.lock().expect("set store").undelete(key)
+    }
+
+    fn rename(&mut self, key: &str, new_key: &str) -> io::Result<()> {
+        self.store.lock().expect("set store").rename(key, new_key)
+    }
+
+    fn touch(&mut self, key: &str) -> io::Result<()> {
+        self.store.lock().expect("set store").touch(key)
+    }
+
+    fn clear_prefix(&mut self, prefix: &str) -> io::Result<usize> {
+        self.store.lock().expect("set store").clear_prefix(prefix)
+    }
+
+    fn filter<F: Fn(&str, &str) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> io::Result<Vec<(String, String)>> {
+        self.store.lock().expect("set store").filter(&predicate)
+    }
+
+    fn scan_glob(&mut self, pattern: &str) -> io::Result<Vec<(String, String)>> {
+        self.store.lock().expect("set store").scan_glob(pattern)
+    }
+
+    fn contains_value(&mut self, value: &str) -> io::Result<bool> {
+        self.store.lock().expect("set store").contains_value(value)
+    }
+
+    fn verify(&mut self) -> io::Result<VerifyReport> {
+        self.store.lock().expect("set store").verify()
+    }
+
+    fn shrink_memory(&mut self) {
+        self.store.lock().expect("set store").shrink_memory()
+    }
+
+    fn keys_count_by_location(&self) -> KeyLocationCounts {
+        self.store
+            .lock()
+            .expect("set store")
+            .keys_count_by_location()
+    }
+
+    fn pending_deletes(&self) -> io::Result<usize> {
+        self.store.lock().expect("set store").pending_deletes()
+    }
+
+    fn size_on_disk(&self) -> io::Result<u64> {
+        self.store.lock().expect("set store").size_on_disk()
+    }
+
+    fn fragmentation(&self) -> io::Result<Vec<(String, f64)>> {
+        self.store.lock().expect("set store").fragmentation()
+    }
+
+    fn stats(&self) -> io::Result<Stats> {
+        self.store.lock().expect("set store").stats()
+    }
+
+    fn stats_json(&self) -> io::Result<String> {
+        Ok(self.stats()?.to_json())
+    }
+
+    fn recent_entries(&self) -> Vec<(String, String)> {
+        self.store.lock().expect("set store").recent_entries()
+    }
+
+    fn iter_data_file(&self, filename: &str) -> io::Result<Vec<(String, String)>> {
+        self.store
+            .lock()
+            .expect("set store")
+            .iter_data_file(filename)
+    }
+
+    fn merge_from(&mut self, other_db_path: &str, policy: MergePolicy) -> io::Result<usize> {
+        self.store
+            .lock()
+            .expect("set store")
+            .merge_from(other_db_path, policy)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.store.lock().expect("set store").clear()
+    }
+
+    fn clear_async(&mut self) -> io::Result<JoinHandle<io::Result<()>>> {
+        self.store.lock().expect("set store").clear_async()
+    }
+
+    fn reload(&mut self) -> io::Result<()> {
+        self.store.lock().expect("set store").reload()
+    }
+}
+
+impl Drop for Ckydb {
+    fn drop(&mut self) {
+        // the database may already have been closed explicitly,
+        // yet sets may have been buffered since then
+        self.flush().unwrap_or(());
+        self.close().unwrap_or(());
+    }
+}
+
+/// Connects to the Ckydb instance, initializing it with its background tasks and returns it.
+/// `max_file_size_kb` is the maximum file size permitted for the database files. Make sure it fits in RAM.
+/// `vacuum_interval_sec` is the time between [vacuuming] cycles for the database. It must be positive.
+/// Since each cycle locks the database while it rewrites the files, intervals of less than
+/// a second are rarely sensible outside tests.
+///
 /// # Errors
 /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
 /// is not accessible
+/// - [InvalidVacuumIntervalError] wrapped in an [io::Error] if `vacuum_interval_sec` is
+///   not a positive number of seconds
 ///
 /// [io::Error]: std::io::Error
 /// [vacuuming]: crate::store::Storage::vacuum
+/// [InvalidVacuumIntervalError]: crate::errors::InvalidVacuumIntervalError
 pub fn connect(
     db_path: &str,
     max_file_size_kb: f64,
     vacuum_interval_sec: f64,
 ) -> io::Result<Ckydb> {
-    let mut db = Ckydb::new(db_path, max_file_size_kb, vacuum_interval_sec)?;
+    connect_with_options(
+        db_path,
+        max_file_size_kb,
+        vacuum_interval_sec,
+        &Options::default(),
+    )
+}
+
+/// Connects to the Ckydb instance just like [connect] does, but with the given [Options]
+/// instead of the default ones.
+///
+/// # Errors
+/// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+///   is not accessible
+/// - [NotADatabaseError] wrapped in an [io::Error] if `options.from_existing` is set yet the
+///   database folder is not an existing ckydb database
+/// - [SymlinkedDbPathError] wrapped in an [io::Error] if `options.refuse_symlinked_db_path`
+///   is set yet the database folder is a symbolic link
+/// - [InvalidVacuumIntervalError] wrapped in an [io::Error] if `vacuum_interval_sec` is
+///   not a positive number of seconds
+/// - [VerificationError] wrapped in an [io::Error] if `options.verify_on_connect` is set
+///   and the database is inconsistent
+/// - [WrongEncryptionKeyError] wrapped in an [io::Error] if `options.encryption_key` is set
+///   yet the saved values cannot be decrypted with it
+///
+/// [io::Error]: std::io::Error
+/// [Options]: crate::options::Options
+/// [NotADatabaseError]: crate::errors::NotADatabaseError
+/// [SymlinkedDbPathError]: crate::errors::SymlinkedDbPathError
+/// [InvalidVacuumIntervalError]: crate::errors::InvalidVacuumIntervalError
+/// [VerificationError]: crate::errors::VerificationError
+/// [WrongEncryptionKeyError]: crate::errors::WrongEncryptionKeyError
+pub fn connect_with_options<P: AsRef<Path>>(
+    db_path: P,
+    max_file_size_kb: f64,
+    vacuum_interval_sec: f64,
+    options: &Options,
+) -> io::Result<Ckydb> {
+    let mut db = Ckydb::new(
+        db_path.as_ref(),
+        max_file_size_kb,
+        vacuum_interval_sec,
+        options,
+    )?;
     db.open().and(Ok(db))
 }
 
+/// Connects to the Ckydb instance just like [connect] does, but accepting any path-like `db_path`
+/// e.g. a [PathBuf], so that paths that are not valid UTF-8 need not be converted to [str] first.
+/// [connect_with_options] accepts such paths too.
+///
+/// # Errors
+///
+/// See [connect]
+///
+/// [PathBuf]: std::path::PathBuf
+pub fn connect_path<P: AsRef<Path>>(
+    db_path: P,
+    max_file_size_kb: f64,
+    vacuum_interval_sec: f64,
+) -> io::Result<Ckydb> {
+    connect_with_options(
+        db_path,
+        max_file_size_kb,
+        vacuum_interval_sec,
+        &Options::default(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::{LoadProgressHook, RollHook};
     use crate::{constants, utils};
     use serial_test::serial;
-    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
     use std::thread::sleep;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     const DB_PATH: &str = "test_controller_db";
     const VACUUM_INTERVAL_SEC: f64 = 2.0;
@@ -269,7 +1059,13 @@ mod tests {
     #[test]
     #[serial]
     fn open_should_start_all_tasks() {
-        let mut db = Ckydb::new(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        let mut db = Ckydb::new(
+            Path::new(DB_PATH),
+            MAX_FILE_SIZE_KB,
+            VACUUM_INTERVAL_SEC,
+            &Options::default(),
+        )
+        .unwrap();
 
         if let Err(err) = db.open() {
             panic!("error opening db: {}", err);
@@ -282,6 +1078,40 @@ mod tests {
         });
     }
 
+    #[test]
+    #[serial]
+    fn connect_path_should_connect_with_path_buf() {
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        let db_path = PathBuf::from(DB_PATH);
+        let mut db = connect_path(&db_path, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.set("foo", "bar").unwrap();
+
+        assert_eq!(db_path.as_path(), db.db_path());
+        assert_eq!("bar", db.get("foo").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn config_should_return_settings_db_was_connected_with() {
+        let options = Options {
+            vacuum_on_load: false,
+            ..Options::default()
+        };
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        let db =
+            connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options).unwrap();
+        let config = db.config();
+
+        assert_eq!(Path::new(DB_PATH), db.db_path());
+        assert_eq!(Path::new(DB_PATH), config.db_path);
+        assert_eq!(MAX_FILE_SIZE_KB, config.max_file_size_kb);
+        assert_eq!(VACUUM_INTERVAL_SEC, config.vacuum_interval_sec);
+        assert_eq!(options, config.options);
+        assert_eq!(constants::KEY_VALUE_SEPARATOR, config.key_value_separator);
+        assert_eq!(constants::TOKEN_SEPARATOR, config.token_separator);
+    }
+
     #[test]
     #[serial]
     fn close_should_stop_all_tasks() {
@@ -299,6 +1129,107 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn flush_should_persist_buffered_sets_for_reopened_db() {
+        let options = Options {
+            flush_every_n: Some(10),
+            ..Options::default()
+        };
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        let mut db =
+            connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options).unwrap();
+
+        for (k, v) in &TEST_RECORDS[..3] {
+            db.set(*k, *v).unwrap_or_else(|_| panic!("set {}", k));
+        }
+
+        let log_file_contents = utils::read_files_with_extension(DB_PATH, "log").unwrap();
+        assert_eq!(vec!["".to_string()], log_file_contents);
+
+        db.flush().expect("flush db");
+        db.set(TEST_RECORDS[3].0, TEST_RECORDS[3].1)
+            .expect("set buffered key");
+        db.close().expect("close db");
+
+        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        for (k, v) in &TEST_RECORDS[..4] {
+            match db.get(*k) {
+                Ok(value) => assert_eq!(*v, value),
+                Err(err) => panic!("error getting key {}: {}", k, err),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn open_already_open_db_should_return_already_running_error() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        match db.open() {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert_eq!(AlreadyRunningError.to_string(), err.to_string()),
+        }
+
+        assert!(db.tasks.as_ref().is_some_and(|tasks| tasks.len() == 1));
+    }
+
+    #[test]
+    #[serial]
+    fn close_already_closed_db_should_return_not_running_error() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        db.close().expect("close db");
+
+        match db.close() {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert_eq!(NotRunningError.to_string(), err.to_string()),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn open_after_close_should_restart_tasks_and_reload_store() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        db.set("hey", "English").expect("set hey");
+
+        db.close().expect("close db");
+        let mut other_db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        other_db.set("hi", "English").expect("set hi");
+        other_db.close().expect("close other db");
+        db.open().expect("reopen db");
+
+        match db.tasks.as_ref() {
+            Some(tasks) => {
+                assert_eq!(1, tasks.len());
+                assert!(!tasks[0].is_finished());
+            }
+            None => panic!("tasks should be restarted"),
+        }
+
+        db.set("salut", "French").expect("set salut");
+
+        for (k, v) in [("hey", "English"), ("hi", "English"), ("salut", "French")] {
+            match db.get(k) {
+                Ok(value) => assert_eq!(v, value),
+                Err(err) => panic!("error getting key {}: {}", k, err),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn close_should_not_wait_for_vacuum_interval() {
+        let vacuum_interval_sec = 60.0;
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, vacuum_interval_sec).unwrap();
+        let start = Instant::now();
+
+        if let Err(err) = db.close() {
+            panic!("error closing db: {}", err);
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
     #[test]
     #[serial]
     fn set_new_key_should_add_key_value_to_store() {
@@ -319,6 +1250,27 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn set_empty_key_should_return_error() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        match db.set("", "v") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("invalid key")),
+        }
+
+        match db.get("") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+
+        match db.delete("") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("invalid key")),
+        }
+    }
+
     #[test]
     #[serial]
     fn set_old_key_should_update_old_key_value() {
@@ -358,103 +1310,573 @@ mod tests {
 
         for (k, v) in &old_records {
             match db.get(*k) {
-                Ok(value) => assert_eq!(*v, value),
-                Err(err) => panic!("error getting keys: {}", err),
-            };
+                Ok(value) => assert_eq!(*v, value),
+                Err(err) => panic!("error getting keys: {}", err),
+            };
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_should_return_value_for_key_in_store() {
+        let (key, value) = ("cow", "500 months");
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).expect("connect");
+
+        match db.get(key) {
+            Ok(v) => assert_eq!(value.to_string(), v),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_again_should_get_value_from_memory_cache() {
+        let (key, value) = ("cow", "500 months");
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).expect("connect");
+
+        if let Err(err) = db.get(key) {
+            panic!("error getting keys: {}", err);
+        }
+
+        // remove the files to ensure data is got from memory only
+        if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
+            panic!("error deleting files: {}", err)
+        }
+
+        match db.get(key) {
+            Ok(v) => assert_eq!(value.to_string(), v),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_newly_inserted_key_should_get_from_memory_memtable() {
+        let (key, value) = ("hello", "world");
+
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        if let Err(err) = db.set(key, value) {
+            panic!("error getting keys: {}", err);
+        }
+
+        // remove the files to ensure data is got from memory only
+        if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
+            panic!("error deleting files: {}", err)
+        }
+
+        match db.get(key) {
+            Ok(v) => assert_eq!(value.to_string(), v),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn try_get_should_return_none_for_missing_key() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        db.set("hey", "English").expect("set hey");
+
+        match db.try_get("hey") {
+            Ok(value) => assert_eq!(Some("English".to_string()), value),
+            Err(err) => panic!("error getting hey: {}", err),
+        }
+
+        match db.try_get("yo") {
+            Ok(value) => assert_eq!(None, value),
+            Err(err) => panic!("error getting yo: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_map_should_return_only_found_keys() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        let expected: HashMap<String, String> = HashMap::from([
+            ("hey".to_string(), "English".to_string()),
+            ("oi".to_string(), "Portuguese".to_string()),
+        ]);
+
+        assert_eq!(expected, db.get_map(&["hey", "yo", "oi", "ciao"]));
+        assert_eq!(HashMap::new(), db.get_map(&[]));
+    }
+
+    #[test]
+    #[serial]
+    fn multi_get_should_return_results_in_order_of_keys() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        let results: Vec<Option<String>> = db
+            .multi_get(&["oi", "yo", "hey", "ciao", "oi"])
+            .into_iter()
+            .map(|result| result.ok())
+            .collect();
+
+        let expected = vec![
+            Some("Portuguese".to_string()),
+            None,
+            Some("English".to_string()),
+            None,
+            Some("Portuguese".to_string()),
+        ];
+        assert_eq!(expected, results);
+        assert!(db.multi_get(&[]).is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn increment_non_existent_key_should_start_from_zero() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        match db.increment("counter", 5) {
+            Ok(value) => assert_eq!(5, value),
+            Err(err) => panic!("error incrementing key: {}", err),
+        }
+
+        match db.get("counter") {
+            Ok(value) => assert_eq!("5", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn increment_existing_key_should_add_delta_to_value() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.set("counter", "40").expect("set counter");
+
+        match db.increment("counter", 2) {
+            Ok(value) => assert_eq!(42, value),
+            Err(err) => panic!("error incrementing key: {}", err),
+        }
+
+        match db.get("counter") {
+            Ok(value) => assert_eq!("42", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn increment_with_negative_delta_should_decrement_value() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.set("counter", "3").expect("set counter");
+
+        match db.increment("counter", -5) {
+            Ok(value) => assert_eq!(-2, value),
+            Err(err) => panic!("error incrementing key: {}", err),
+        }
+
+        match db.get("counter") {
+            Ok(value) => assert_eq!("-2", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn increment_non_numeric_value_should_return_error() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.set("counter", "forty").expect("set counter");
+
+        match db.increment("counter", 1) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not a valid 64-bit integer")),
+        }
+
+        match db.get("counter") {
+            Ok(value) => assert_eq!("forty", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn append_should_accumulate_suffixes_onto_value() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (suffix, expected) in [("a", "a"), ("bc", "abc"), ("", "abc"), ("def", "abcdef")] {
+            match db.append("log", suffix) {
+                Ok(value) => assert_eq!(expected, value),
+                Err(err) => panic!("error appending to key: {}", err),
+            }
+        }
+
+        match db.get("log") {
+            Ok(value) => assert_eq!("abcdef", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_or_set_should_persist_default_only_for_missing_key() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        assert_eq!("dark", db.get_or_set("theme", "dark").expect("init theme"));
+        assert_eq!("dark", db.get_or_set("theme", "light").expect("get theme"));
+        db.close().expect("close db");
+
+        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        match db.get("theme") {
+            Ok(value) => assert_eq!("dark", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+        assert_eq!("dark", db.get_or_set("theme", "light").expect("get theme"));
+    }
+
+    #[test]
+    #[serial]
+    fn insert_new_should_only_add_missing_keys() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.insert_new("id-1", "foo").expect("insert new id-1");
+        match db.insert_new("id-1", "bar") {
+            Ok(_) => panic!("second insert_new of id-1 should fail"),
+            Err(err) => assert_eq!(ErrorKind::AlreadyExists, err.kind()),
+        }
+        assert_eq!("foo", db.get("id-1").expect("get id-1"));
+
+        db.set("id-1", "bar").expect("set id-1");
+        assert_eq!("bar", db.get("id-1").expect("get id-1"));
+    }
+
+    #[test]
+    #[serial]
+    fn status_should_report_present_deleted_and_absent_keys() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        if let Err(err) = db.delete("hey") {
+            panic!("error deleting keys: {}", err)
+        }
+
+        for (k, expected) in [
+            ("hi", KeyStatus::Present),
+            ("hey", KeyStatus::Deleted),
+            ("yo", KeyStatus::Absent),
+        ] {
+            match db.status(k) {
+                Ok(status) => assert_eq!(expected, status),
+                Err(err) => panic!("error getting status: {}", err),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_should_remove_key_value_from_store() {
+        let mut old_records = HashMap::from(TEST_RECORDS);
+        let keys_to_delete = ["hey", "salut"];
+
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in &old_records {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        for k in &keys_to_delete {
+            match db.delete(*k) {
+                Ok(value) => {
+                    assert_eq!(old_records.remove(*k), Some(value.as_str()));
+                }
+                Err(err) => panic!("error deleting keys: {}", err),
+            }
+        }
+
+        for (k, v) in &old_records {
+            match db.get(*k) {
+                Ok(value) => assert_eq!(*v, value),
+                Err(err) => panic!("error getting keys: {}", err),
+            };
+        }
+
+        for k in &keys_to_delete {
+            match db.get(*k) {
+                Ok(_) => panic!("key: {} unexpected", k),
+                Err(err) => assert!(err.to_string().contains("not found")),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_if_should_remove_key_only_if_predicate_holds_for_its_value() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        db.set("session-1", "expired").expect("set session-1");
+        db.set("session-2", "active").expect("set session-2");
+
+        assert!(db
+            .delete_if("session-1", |value| value == "expired")
+            .expect("delete_if session-1"));
+        assert!(!db
+            .delete_if("session-2", |value| value == "expired")
+            .expect("delete_if session-2"));
+        assert!(!db
+            .delete_if("session-3", |_| true)
+            .expect("delete_if session-3"));
+
+        match db.get("session-1") {
+            Ok(_) => panic!("key: session-1 unexpected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+        assert_eq!("active", db.get("session-2").expect("get session-2"));
+        assert_eq!(KeyStatus::Absent, db.status("session-3").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn rename_should_move_value_to_new_key() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        if let Err(err) = db.rename("hey", "oi") {
+            panic!("error renaming keys: {}", err);
+        }
+
+        match db.get("oi") {
+            Ok(value) => assert_eq!("English", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+
+        match db.get("hey") {
+            Ok(_) => panic!("key: hey unexpected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn clear_prefix_should_remove_only_key_values_with_given_prefix() {
+        let session_records = [("session:1", "Jane"), ("session:2", "John")];
+        let user_records = [("user:1", "Jane"), ("user:2", "John"), ("users", "all")];
+
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+
+        for (k, v) in session_records.iter().chain(&user_records) {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        match db.clear_prefix("session:") {
+            Ok(count) => assert_eq!(session_records.len(), count),
+            Err(err) => panic!("error clearing prefix: {}", err),
+        }
+
+        match db.clear_prefix("user:") {
+            Ok(count) => assert_eq!(2, count),
+            Err(err) => panic!("error clearing prefix: {}", err),
+        }
+
+        match db.clear_prefix("admin:") {
+            Ok(count) => assert_eq!(0, count),
+            Err(err) => panic!("error clearing prefix: {}", err),
+        }
+
+        for (k, _) in session_records.iter().chain(&user_records[..2]) {
+            match db.get(*k) {
+                Ok(_) => panic!("key: {} unexpected", k),
+                Err(err) => assert!(err.to_string().contains("not found")),
+            }
+        }
+
+        match db.get("users") {
+            Ok(value) => assert_eq!("all", value),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn set_many_from_iter_should_add_all_pairs_yielded_by_iterator() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        let pairs = TEST_RECORDS
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        match db.set_many_from_iter(pairs) {
+            Ok(count) => assert_eq!(TEST_RECORDS.len(), count),
+            Err(err) => panic!("error setting many keys: {}", err),
+        }
+
+        for (k, v) in &TEST_RECORDS {
+            match db.get(*k) {
+                Ok(got) => assert_eq!(*v, got),
+                Err(err) => panic!("error getting key {}: {}", k, err),
+            }
         }
     }
 
     #[test]
     #[serial]
-    fn get_old_key_should_return_value_for_key_in_store() {
-        let (key, value) = ("cow", "500 months");
-        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
-        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).expect("connect");
+    fn contains_value_should_find_duplicated_value_and_not_absent_value() {
+        let mut db =
+            connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB * 2.5, VACUUM_INTERVAL_SEC).unwrap();
 
-        match db.get(key) {
-            Ok(v) => assert_eq!(value.to_string(), v),
-            Err(err) => panic!("error getting keys: {}", err),
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        for (value, expected) in [("English", true), ("Klingon", false)] {
+            match db.contains_value(value) {
+                Ok(got) => assert_eq!(expected, got, "{}", value),
+                Err(err) => panic!("error checking value: {}", err),
+            }
         }
     }
 
     #[test]
     #[serial]
-    fn get_old_key_again_should_get_value_from_memory_cache() {
-        let (key, value) = ("cow", "500 months");
+    fn filter_should_return_key_values_matching_predicate() {
+        let mut db =
+            connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB * 2.5, VACUUM_INTERVAL_SEC).unwrap();
 
-        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
-        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).expect("connect");
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
 
-        if let Err(err) = db.get(key) {
-            panic!("error getting keys: {}", err);
+        match db.filter(|_, value| value.ends_with("ish")) {
+            Ok(results) => assert_eq!(
+                vec![
+                    ("hey".to_string(), "English".to_string()),
+                    ("hi".to_string(), "English".to_string()),
+                    ("hola".to_string(), "Spanish".to_string()),
+                ],
+                results
+            ),
+            Err(err) => panic!("error filtering: {}", err),
         }
+    }
 
-        // remove the files to ensure data is got from memory only
-        if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
-            panic!("error deleting files: {}", err)
+    #[test]
+    #[serial]
+    fn scan_glob_should_return_key_values_whose_keys_match_pattern() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        let records = [
+            ("user:1:active", "Ann"),
+            ("user:2:inactive", "Ben"),
+            ("admin:3:active", "Cat"),
+            ("user:4:active", "Dan"),
+        ];
+
+        for (k, v) in &records {
+            db.set(*k, *v).expect("set key");
         }
 
-        match db.get(key) {
-            Ok(v) => assert_eq!(value.to_string(), v),
-            Err(err) => panic!("error getting keys: {}", err),
+        match db.scan_glob("user:*:active") {
+            Ok(results) => assert_eq!(
+                vec![
+                    ("user:1:active".to_string(), "Ann".to_string()),
+                    ("user:4:active".to_string(), "Dan".to_string()),
+                ],
+                results
+            ),
+            Err(err) => panic!("error scanning: {}", err),
         }
+        assert_eq!(4, db.scan_glob("*:?:*").unwrap().len());
+        assert!(db.scan_glob("user:?").unwrap().is_empty());
     }
 
     #[test]
     #[serial]
-    fn get_newly_inserted_key_should_get_from_memory_memtable() {
-        let (key, value) = ("hello", "world");
-
+    fn size_on_disk_should_grow_after_sets_and_shrink_after_clear() {
         let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        let initial_size = db.size_on_disk().expect("get initial size");
 
-        if let Err(err) = db.set(key, value) {
-            panic!("error getting keys: {}", err);
+        for (k, v) in &TEST_RECORDS {
+            db.set(*k, *v).unwrap_or_else(|_| panic!("set {}", k));
         }
+        let size_after_sets = db.size_on_disk().expect("get size after sets");
 
-        // remove the files to ensure data is got from memory only
-        if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
-            panic!("error deleting files: {}", err)
-        }
+        db.clear().expect("clear db");
+        let size_after_clear = db.size_on_disk().expect("get size after clear");
 
-        match db.get(key) {
-            Ok(v) => assert_eq!(value.to_string(), v),
-            Err(err) => panic!("error getting keys: {}", err),
-        }
+        assert!(size_after_sets > initial_size);
+        assert!(size_after_clear < size_after_sets);
     }
 
     #[test]
     #[serial]
-    fn delete_should_remove_key_value_from_store() {
-        let mut old_records = HashMap::from(TEST_RECORDS);
-        let keys_to_delete = ["hey", "salut"];
+    fn stats_json_should_be_a_json_object_of_the_stats() {
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, 60.0).unwrap();
+
+        for (k, v) in &TEST_RECORDS {
+            db.set(*k, *v).unwrap_or_else(|_| panic!("set {}", k));
+        }
+        db.get("hey").expect("get hey");
+        db.delete("hi").expect("delete hi");
+
+        let stats = db.stats().expect("get stats");
+        let json: serde_json::Value =
+            serde_json::from_str(&db.stats_json().expect("get stats json")).expect("parse json");
+
+        assert_eq!(TEST_RECORDS.len() - 1, stats.keys);
+        assert_eq!(1, stats.pending_deletes);
+        assert!(stats.cache_hits >= 1);
+        assert_eq!(db.size_on_disk().unwrap(), stats.size_on_disk_bytes);
+        assert_eq!(stats.keys as u64, json["keys"]);
+        assert_eq!(stats.data_files as u64, json["data_files"]);
+        assert_eq!(stats.size_on_disk_bytes, json["size_on_disk_bytes"]);
+        assert_eq!(stats.pending_deletes as u64, json["pending_deletes"]);
+        assert_eq!(stats.cache_hits, json["cache_hits"]);
+        assert_eq!(stats.cache_misses, json["cache_misses"]);
+    }
 
+    #[test]
+    #[serial]
+    fn clear_should_remove_all_key_values_from_store() {
         let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
 
-        for (k, v) in &old_records {
+        for (k, v) in &TEST_RECORDS {
             if let Err(err) = db.set(*k, *v) {
                 panic!("error setting keys: {}", err);
             };
         }
 
-        for k in &keys_to_delete {
-            match db.delete(*k) {
-                Ok(_) => {
-                    old_records.remove(*k);
-                }
-                Err(err) => panic!("error deleting keys: {}", err),
-            }
-        }
-
-        for (k, v) in &old_records {
-            match db.get(*k) {
-                Ok(value) => assert_eq!(*v, value),
-                Err(err) => panic!("error getting keys: {}", err),
-            };
+        if let Err(err) = db.clear() {
+            panic!("error clearing db: {}", err)
         }
 
-        for k in &keys_to_delete {
+        for (k, _) in &TEST_RECORDS {
             match db.get(*k) {
                 Ok(_) => panic!("key: {} unexpected", k),
                 Err(err) => assert!(err.to_string().contains("not found")),
@@ -464,7 +1886,41 @@ mod tests {
 
     #[test]
     #[serial]
-    fn clear_should_remove_all_key_values_from_store() {
+    fn clear_should_not_race_with_vacuums_of_concurrent_deletes() {
+        let db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, 0.01).unwrap();
+        let db = Arc::new(Mutex::new(db));
+
+        let writer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                for i in 0..100 {
+                    let key = format!("key-{}", i);
+                    let mut db = db.lock().expect("lock db");
+                    db.set(&key, "v").unwrap_or_else(|_| panic!("set {}", key));
+                    db.delete(&key).unwrap_or_else(|_| panic!("delete {}", key));
+                    drop(db);
+                    sleep(Duration::from_millis(1));
+                }
+            })
+        };
+
+        for _ in 0..5 {
+            db.lock().expect("lock db").clear().expect("clear db");
+            sleep(Duration::from_millis(10));
+        }
+        writer.join().expect("join writer");
+
+        let mut db = db.lock().expect("lock db");
+        db.clear().expect("clear db");
+        db.set("hi", "English").expect("set hi");
+        assert_eq!("English", db.get("hi").expect("get hi"));
+        sleep(Duration::from_millis(50));
+        assert!(db.verify().expect("verify db").is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn clear_async_should_empty_store_immediately_and_remove_old_data_later() {
         let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
 
         for (k, v) in &TEST_RECORDS {
@@ -473,9 +1929,7 @@ mod tests {
             };
         }
 
-        if let Err(err) = db.clear() {
-            panic!("error clearing db: {}", err)
-        }
+        let task = db.clear_async().expect("clear db");
 
         for (k, _) in &TEST_RECORDS {
             match db.get(*k) {
@@ -483,6 +1937,22 @@ mod tests {
                 Err(err) => assert!(err.to_string().contains("not found")),
             }
         }
+        let cky_files = utils::read_files_with_extension(DB_PATH, "cky").unwrap();
+        assert!(cky_files.is_empty());
+
+        task.join().expect("join task").expect("remove old data");
+        let old_folders = fs::read_dir(".")
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                file_name.starts_with(&format!("{}.cleared-", DB_PATH))
+            })
+            .count();
+        assert_eq!(0, old_folders);
+
+        db.set("hi", "English").expect("set hi");
+        assert_eq!("English", db.get("hi").expect("get hi"));
     }
 
     #[test]
@@ -528,6 +1998,310 @@ mod tests {
         assert!(!log_file_contents_post_vacuum[0].contains(key_to_delete));
     }
 
+    #[test]
+    #[serial]
+    fn connect_without_vacuum_on_load_should_leave_vacuuming_to_background_task() {
+        let options = Options {
+            vacuum_on_load: false,
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let _db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+
+        let del_file_contents_pre_vacuum =
+            utils::read_files_with_extension(DB_PATH, "del").unwrap();
+
+        sleep(Duration::from_secs_f64(VACUUM_INTERVAL_SEC * 2.0));
+
+        let del_file_contents_post_vacuum =
+            utils::read_files_with_extension(DB_PATH, "del").unwrap();
+
+        assert!(del_file_contents_pre_vacuum[0].contains("foo"));
+        assert!(del_file_contents_pre_vacuum[0].contains("bar"));
+        assert_eq!("", del_file_contents_post_vacuum[0]);
+    }
+
+    #[test]
+    #[serial]
+    fn connect_should_fail_if_vacuum_interval_is_not_positive() {
+        for vacuum_interval_sec in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            match connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, vacuum_interval_sec) {
+                Ok(_) => panic!("error was expected for {}", vacuum_interval_sec),
+                Err(err) => assert!(err.to_string().contains("invalid vacuum interval")),
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn connect_from_existing_should_fail_if_folder_is_not_a_database() {
+        let options = Options {
+            from_existing: true,
+            ..Default::default()
+        };
+        let other_file_path = Path::new(DB_PATH).join("notes.txt");
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        fs::create_dir_all(DB_PATH).expect("create folder");
+        fs::write(&other_file_path, "some notes").expect("write other file");
+
+        match connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not a ckydb database")),
+        }
+
+        let files = utils::get_file_names_in_folder(DB_PATH).expect("get files in folder");
+        assert_eq!(vec!["notes.txt".to_string()], files);
+    }
+
+    #[test]
+    #[serial]
+    fn connect_from_existing_should_open_an_existing_database() {
+        let (key, value) = ("cow", "500 months");
+        let options = Options {
+            from_existing: true,
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+
+        match db.get(key) {
+            Ok(v) => assert_eq!(value.to_string(), v),
+            Err(err) => panic!("error getting keys: {}", err),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn connect_with_max_log_entries_should_roll_log_file_that_has_more_entries() {
+        let options = Options {
+            max_log_entries: Some(2),
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+        let count_data_files = || {
+            utils::get_file_names_in_folder(DB_PATH)
+                .expect("get file names")
+                .iter()
+                .filter(|name| name.ends_with(".cky"))
+                .count()
+        };
+        let data_files_before = count_data_files();
+
+        // the log file already holds more than 2 entries so the next set rolls it
+        db.set("sheep", "2 months").expect("set sheep");
+
+        assert_eq!(data_files_before + 1, count_data_files());
+        assert_eq!("2 months", db.get("sheep").expect("get sheep"));
+    }
+
+    #[test]
+    #[serial]
+    fn connect_with_roll_hook_should_run_it_when_log_file_is_rolled() {
+        let rolled_files = Arc::new(Mutex::new(vec![]));
+        let hook_rolled_files = Arc::clone(&rolled_files);
+        let options = Options {
+            max_log_entries: Some(2),
+            roll_hook: Some(RollHook::new(move |data_file| {
+                hook_rolled_files
+                    .lock()
+                    .unwrap()
+                    .push(data_file.to_string());
+                Ok(())
+            })),
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+        assert!(rolled_files.lock().unwrap().is_empty());
+
+        db.set("sheep", "2 months").expect("set sheep");
+
+        let rolled_files = rolled_files.lock().unwrap();
+        assert_eq!(1, rolled_files.len());
+        assert!(Path::new(DB_PATH).join(&rolled_files[0]).exists());
+    }
+
+    #[test]
+    #[serial]
+    fn connect_with_load_progress_hook_should_run_it_on_connecting_and_reloading() {
+        let progress = Arc::new(Mutex::new(vec![]));
+        let hook_progress = Arc::clone(&progress);
+        let options = Options {
+            vacuum_on_load: false,
+            load_progress_hook: Some(LoadProgressHook::new(
+                move |files_processed, total_files| {
+                    hook_progress
+                        .lock()
+                        .unwrap()
+                        .push((files_processed, total_files));
+                },
+            )),
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+
+        // the index, del and log files, and the two data files
+        let expected: Vec<(usize, usize)> = (1..=5).map(|i| (i, 5)).collect();
+        assert_eq!(expected, *progress.lock().unwrap());
+
+        progress.lock().unwrap().clear();
+        db.reload().expect("reload");
+        assert_eq!(expected, *progress.lock().unwrap());
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(unix)]
+    fn connect_with_refuse_symlinked_db_path_should_fail_for_symlinked_folder() {
+        let target_path = format!("{}_target", DB_PATH);
+        let link_path = format!("{}_link", DB_PATH);
+        let options = Options {
+            refuse_symlinked_db_path: true,
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(&target_path).expect("clear dummy data");
+        fs::remove_file(&link_path).ok();
+        utils::add_dummy_file_data_in_db(&target_path).expect("add dummy data");
+        std::os::unix::fs::symlink(&target_path, &link_path).expect("create symlink");
+
+        match connect_with_options(&link_path, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("symlinked database folder")),
+        }
+        assert!(connect_with_options(
+            &target_path,
+            MAX_FILE_SIZE_KB,
+            VACUUM_INTERVAL_SEC,
+            &options
+        )
+        .is_ok());
+        assert!(connect(&link_path, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).is_ok());
+
+        fs::remove_file(&link_path).expect("remove symlink");
+        utils::clear_dummy_file_data_in_db(&target_path).expect("clear dummy data");
+    }
+
+    #[test]
+    #[serial]
+    fn connect_with_verify_on_connect_should_fail_for_inconsistent_database() {
+        let options = Options {
+            verify_on_connect: true,
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("add dummy data");
+        connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect to consistent database")
+            .close()
+            .expect("close db");
+
+        fs::remove_file(Path::new(DB_PATH).join("01655375120328185000.cky"))
+            .expect("remove data file");
+
+        match connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => {
+                assert!(err.to_string().contains("verification failed"));
+                assert!(err.to_string().contains("1655375120328185"));
+            }
+        }
+
+        connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).expect("connect without verifying");
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "encryption")]
+    fn connect_with_wrong_encryption_key_should_fail() {
+        let options = Options {
+            encryption_key: Some([7; 32]),
+            ..Default::default()
+        };
+
+        let mut db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC)
+            .expect("connect to db");
+        db.close().expect("close db");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect with encryption key");
+        db.set("cow", "500 months").expect("set cow");
+        db.close().expect("close db");
+
+        let wrong_options = Options {
+            encryption_key: Some([8; 32]),
+            ..Default::default()
+        };
+        match connect_with_options(
+            DB_PATH,
+            MAX_FILE_SIZE_KB,
+            VACUUM_INTERVAL_SEC,
+            &wrong_options,
+        ) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("wrong encryption key")),
+        }
+
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect with right encryption key");
+        assert_eq!("500 months", db.get("cow").expect("get cow"));
+    }
+
+    #[test]
+    #[serial]
+    fn connect_in_memory_should_not_persist_anything_on_disk() {
+        let options = Options {
+            in_memory: true,
+            ..Default::default()
+        };
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clear dummy data");
+        let mut db = connect_with_options(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC, &options)
+            .expect("connect");
+
+        for (k, v) in &TEST_RECORDS {
+            if let Err(err) = db.set(*k, *v) {
+                panic!("error setting keys: {}", err);
+            };
+        }
+
+        if let Err(err) = db.delete("hey") {
+            panic!("error deleting keys: {}", err)
+        }
+
+        for (k, v) in &TEST_RECORDS[1..] {
+            match db.get(*k) {
+                Ok(value) => assert_eq!(value, (*v).to_string()),
+                Err(err) => panic!("error getting keys: {}", err),
+            }
+        }
+
+        match db.get("hey") {
+            Ok(_) => panic!("key: hey unexpected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+
+        assert!(!Path::new(DB_PATH).exists());
+    }
+
     #[test]
     #[serial]
     fn log_file_should_be_turned_to_cky_file_when_it_exceeds_max_size() {
@@ -582,6 +2356,51 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn concurrent_sets_and_deletes_should_not_lose_index_entries() {
+        let db = connect_to_test_db(DB_PATH, MAX_FILE_SIZE_KB, 0.1).unwrap();
+        let db = Arc::new(Mutex::new(db));
+        let number_of_threads = 4;
+        let keys_per_thread = 25;
+
+        let handles: Vec<JoinHandle<()>> = (0..number_of_threads)
+            .map(|t| {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    for i in 0..keys_per_thread {
+                        let key = format!("{}-{}", t, i);
+                        let mut db = db.lock().expect("lock db");
+                        db.set(&key, "v").unwrap_or_else(|_| panic!("set {}", key));
+                        if i % 2 == 0 {
+                            db.delete(&key).unwrap_or_else(|_| panic!("delete {}", key));
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("join thread");
+        }
+
+        db.lock().expect("lock db").close().expect("close db");
+        let mut db = connect(DB_PATH, MAX_FILE_SIZE_KB, VACUUM_INTERVAL_SEC).unwrap();
+        let index_file_content =
+            fs::read_to_string(Path::new(DB_PATH).join(constants::INDEX_FILENAME)).unwrap();
+
+        assert_eq!(
+            number_of_threads * (keys_per_thread / 2),
+            utils::extract_tokens_from_str(&index_file_content).len()
+        );
+        for t in 0..number_of_threads {
+            for i in 0..keys_per_thread {
+                let key = format!("{}-{}", t, i);
+                assert_eq!(i % 2 != 0, db.get(&key).is_ok(), "{}", key);
+            }
+        }
+    }
+
     /// Connects to the test database; first clearing out any dummy data
     ///
     /// # Errors
@@ -596,9 +2415,139 @@ mod tests {
         // utils::add_dummy_file_data_in_db(db_path)?;
         connect(db_path, max_file_size_kb, vacuum_interval_sec)
     }
+
+    #[test]
+    fn ckydb_should_run_over_any_storage() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let storage = MockStorage {
+            calls: Arc::clone(&calls),
+            data: Default::default(),
+        };
+        let config = DbConfig::new(
+            Path::new("mock_db"),
+            MAX_FILE_SIZE_KB,
+            60.0,
+            &Options::default(),
+        );
+
+        let mut db = Ckydb::with_storage(Box::new(storage), config).expect("create db");
+        db.set("foo", "bar").expect("set foo");
+        assert_eq!("bar", db.get("foo").expect("get foo"));
+        db.delete("foo").expect("delete foo");
+        assert!(db.get("foo").is_err());
+
+        assert_eq!(
+            vec!["load", "set foo", "get foo", "delete foo", "get foo"],
+            *calls.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn ckydb_should_run_default_storage_operations_over_any_storage() {
+        let storage = MockStorage {
+            calls: Default::default(),
+            data: Default::default(),
+        };
+        let config = DbConfig::new(
+            Path::new("mock_db"),
+            MAX_FILE_SIZE_KB,
+            60.0,
+            &Options::default(),
+        );
+
+        let mut db = Ckydb::with_storage(Box::new(storage), config).expect("create db");
+        db.insert_new("cow", "500 months").expect("insert cow");
+        assert!(db.insert_new("cow", "1 month").is_err());
+        db.set_many_from_iter(vec![
+            ("dog".to_string(), "23 months".to_string()),
+            ("goat".to_string(), "678 months".to_string()),
+        ])
+        .expect("set many");
+        db.rename("dog", "puppy").expect("rename dog");
+        let snapshot = db.snapshot();
+
+        assert_eq!(KeyStatus::Absent, db.status("dog").expect("status dog"));
+        assert_eq!("23 months", db.get("puppy").expect("get puppy"));
+        assert_eq!(
+            vec![("goat".to_string(), "678 months".to_string())],
+            db.scan_glob("go*").expect("scan glob")
+        );
+        assert!(db.contains_value("500 months").expect("contains value"));
+        assert_eq!(1, db.clear_prefix("co").expect("clear prefix"));
+        assert!(db.get("cow").is_err());
+        assert!(db.undelete("cow").is_err());
+        assert_eq!(
+            "500 months",
+            snapshot.get("cow").expect("get cow from snapshot")
+        );
+    }
+
+    /// A storage that keeps its data in a map and records the calls made to it
+    struct MockStorage {
+        calls: Arc<Mutex<Vec<String>>>,
+        data: HashMap<String, String>,
+    }
+
+    impl MockStorage {
+        fn record(&self, call: &str) {
+            self.calls.lock().unwrap().push(call.to_string());
+        }
+    }
+
+    impl Storage for MockStorage {
+        fn load(&mut self) -> io::Result<()> {
+            self.record("load");
+            Ok(())
+        }
+
+        fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+            self.record(&format!("set {}", key));
+            self.data.insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn get(&mut self, key: &str) -> Result<String, NotFoundError> {
+            self.record(&format!("get {}", key));
+            self.data.get(key).cloned().ok_or(NotFoundError)
+        }
+
+        fn snapshot(&mut self) -> Snapshot {
+            Snapshot::from_pairs(self.data.clone().into_iter().collect())
+        }
+
+        fn delete(&mut self, key: &str) -> io::Result<String> {
+            self.record(&format!("delete {}", key));
+            Ok(self.data.remove(key).ok_or(NotFoundError)?)
+        }
+
+        fn filter(
+            &mut self,
+            predicate: &dyn Fn(&str, &str) -> bool,
+        ) -> io::Result<Vec<(String, String)>> {
+            self.record("filter");
+            let mut pairs: Vec<(String, String)> = self
+                .data
+                .iter()
+                .filter(|(key, value)| predicate(key, value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            pairs.sort();
+            Ok(pairs)
+        }
+
+        fn clear(&mut self) -> io::Result<()> {
+            self.record("clear");
+            self.data.clear();
+            Ok(())
+        }
+
+        fn vacuum(&mut self) -> io::Result<()> {
+            self.record("vacuum");
+            Ok(())
+        }
+    }
 }
 
 pub(crate) enum Signal {
     Stop,
-    Continue,
 }
@@ -0,0 +1,79 @@
+use crate::errors::CorruptedDataError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+/// The size in bytes of the nonce saved before each encrypted value
+const NONCE_SIZE: usize = 12;
+
+/// Encrypts the given `value` with AES-256-GCM using the given `key` and a random nonce,
+/// returning the nonce and the ciphertext as a hexadecimal string so that it can be saved
+/// in the database files like any other value
+///
+/// # Errors
+///
+/// It will return a [CorruptedDataError] in case the value could not be encrypted
+// #[inline]
+pub(crate) fn encrypt_value(value: &str, key: &[u8; 32]) -> Result<String, CorruptedDataError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, value.as_bytes())
+        .or(Err(CorruptedDataError))?;
+
+    Ok(nonce
+        .iter()
+        .chain(ciphertext.iter())
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Decrypts the given `value` encrypted by [encrypt_value] using the given `key`
+///
+/// # Errors
+///
+/// It will return a [CorruptedDataError] in case the value is not a valid encrypted value
+/// or it was encrypted with a different key
+// #[inline]
+pub(crate) fn decrypt_value(value: &str, key: &[u8; 32]) -> Result<String, CorruptedDataError> {
+    if !value.len().is_multiple_of(2) || value.len() < NONCE_SIZE * 2 {
+        return Err(CorruptedDataError);
+    }
+
+    let bytes = (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2).unwrap_or_default(), 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .or(Err(CorruptedDataError))?;
+    let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .or(Err(CorruptedDataError))?;
+
+    String::from_utf8(plaintext).or(Err(CorruptedDataError))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn encrypt_value_round_trips_only_with_same_key() {
+        let encrypted = crypto::encrypt_value("500 months", &KEY).expect("encrypt value");
+
+        assert!(!encrypted.contains("500 months"));
+        assert_ne!(
+            encrypted,
+            crypto::encrypt_value("500 months", &KEY).expect("encrypt value again")
+        );
+        assert_eq!(
+            "500 months",
+            crypto::decrypt_value(&encrypted, &KEY).expect("decrypt value")
+        );
+        assert!(crypto::decrypt_value(&encrypted, &[8; 32]).is_err());
+        assert!(crypto::decrypt_value("500 months", &KEY).is_err());
+    }
+}
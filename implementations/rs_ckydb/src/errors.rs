@@ -1,5 +1,7 @@
+use crate::store::Inconsistency;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind};
 
 /// Error thrown when key is not found in store
 #[derive(Debug, Clone)]
@@ -13,6 +15,12 @@ impl Display for NotFoundError {
 
 impl Error for NotFoundError {}
 
+impl From<NotFoundError> for io::Error {
+    fn from(err: NotFoundError) -> Self {
+        io::Error::new(ErrorKind::NotFound, err)
+    }
+}
+
 /// Error thrown when the data in the database is inconsistent
 #[derive(Debug, Clone)]
 pub struct CorruptedDataError;
@@ -28,6 +36,12 @@ impl Display for CorruptedDataError {
 
 impl Error for CorruptedDataError {}
 
+impl From<CorruptedDataError> for io::Error {
+    fn from(err: CorruptedDataError) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
 /// Error thrown when a back ground tasks is already running
 /// and an attempt is made to start it again
 #[derive(Debug, Clone)]
@@ -41,6 +55,12 @@ impl Display for AlreadyRunningError {
 
 impl Error for AlreadyRunningError {}
 
+impl From<AlreadyRunningError> for io::Error {
+    fn from(err: AlreadyRunningError) -> Self {
+        io::Error::other(err)
+    }
+}
+
 /// Error thrown when a background task is not running
 /// and an attempt to stop it
 #[derive(Debug, Clone)]
@@ -53,3 +73,323 @@ impl Display for NotRunningError {
 }
 
 impl Error for NotRunningError {}
+
+impl From<NotRunningError> for io::Error {
+    fn from(err: NotRunningError) -> Self {
+        io::Error::other(err)
+    }
+}
+
+/// Error thrown when the database folder is expected to be an existing
+/// ckydb database but it is not
+#[derive(Debug, Clone)]
+pub struct NotADatabaseError;
+
+impl Display for NotADatabaseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "not a ckydb database: index file or del file not found in database folder"
+        )
+    }
+}
+
+impl Error for NotADatabaseError {}
+
+impl From<NotADatabaseError> for io::Error {
+    fn from(err: NotADatabaseError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Error thrown when the database folder is a symbolic link yet symbolic links are refused
+#[derive(Debug, Clone)]
+pub struct SymlinkedDbPathError;
+
+impl Display for SymlinkedDbPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "symlinked database folder: database folder is a symbolic link to another folder"
+        )
+    }
+}
+
+impl Error for SymlinkedDbPathError {}
+
+impl From<SymlinkedDbPathError> for io::Error {
+    fn from(err: SymlinkedDbPathError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Error thrown when a value is expected to be a 64-bit integer but it is not
+#[derive(Debug, Clone)]
+pub struct NotAnIntegerError;
+
+impl Display for NotAnIntegerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "value is not a valid 64-bit integer")
+    }
+}
+
+impl Error for NotAnIntegerError {}
+
+impl From<NotAnIntegerError> for io::Error {
+    fn from(err: NotAnIntegerError) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// Error thrown when a key is not acceptable e.g. it is empty
+#[derive(Debug, Clone)]
+pub struct InvalidKeyError;
+
+impl Display for InvalidKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid key: key should not be empty")
+    }
+}
+
+impl Error for InvalidKeyError {}
+
+impl From<InvalidKeyError> for io::Error {
+    fn from(err: InvalidKeyError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Error thrown when a value is bigger than the maximum value size allowed
+#[derive(Debug, Clone)]
+pub struct ValueTooLargeError;
+
+impl Display for ValueTooLargeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "value too large: value exceeds the maximum value size")
+    }
+}
+
+impl Error for ValueTooLargeError {}
+
+impl From<ValueTooLargeError> for io::Error {
+    fn from(err: ValueTooLargeError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Error thrown when the last-modified timestamp of a key is requested but it was not recorded
+/// e.g. the key was set without [Options::track_last_modified] being on
+///
+/// [Options::track_last_modified]: crate::options::Options::track_last_modified
+#[derive(Debug, Clone)]
+pub struct NotTrackedError;
+
+impl Display for NotTrackedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not tracked: last-modified timestamp was not recorded")
+    }
+}
+
+impl Error for NotTrackedError {}
+
+impl From<NotTrackedError> for io::Error {
+    fn from(err: NotTrackedError) -> Self {
+        io::Error::new(ErrorKind::Unsupported, err)
+    }
+}
+
+/// Error thrown when [verifying] the database on connecting finds an `inconsistency`,
+/// the first of those found
+///
+/// [verifying]: crate::options::Options::verify_on_connect
+#[derive(Debug, Clone)]
+pub struct VerificationError {
+    pub inconsistency: Inconsistency,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "verification failed: {}", self.inconsistency)
+    }
+}
+
+impl Error for VerificationError {}
+
+impl From<VerificationError> for io::Error {
+    fn from(err: VerificationError) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// Error thrown when the hook run after the log file is rolled into `data_file` fails.
+/// The roll itself will have succeeded
+#[derive(Debug, Clone)]
+pub struct RollHookError {
+    pub data_file: String,
+    pub message: String,
+}
+
+impl Display for RollHookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "roll hook failed for data file {}: {}",
+            self.data_file, self.message
+        )
+    }
+}
+
+impl Error for RollHookError {}
+
+impl From<RollHookError> for io::Error {
+    fn from(err: RollHookError) -> Self {
+        io::Error::other(err)
+    }
+}
+
+/// Error thrown when the interval between vacuuming cycles is not a positive number of seconds
+#[derive(Debug, Clone)]
+pub struct InvalidVacuumIntervalError;
+
+impl Display for InvalidVacuumIntervalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid vacuum interval: vacuum_interval_sec should be a positive number of seconds"
+        )
+    }
+}
+
+impl Error for InvalidVacuumIntervalError {}
+
+impl From<InvalidVacuumIntervalError> for io::Error {
+    fn from(err: InvalidVacuumIntervalError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+/// Error thrown when inserting a key that is already in the store
+#[derive(Debug, Clone)]
+pub struct AlreadyExistsError {
+    pub key: String,
+}
+
+impl Display for AlreadyExistsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "already exists: key {}", self.key)
+    }
+}
+
+impl Error for AlreadyExistsError {}
+
+impl From<AlreadyExistsError> for io::Error {
+    fn from(err: AlreadyExistsError) -> Self {
+        io::Error::new(ErrorKind::AlreadyExists, err)
+    }
+}
+
+/// Error thrown when writing to a store that is in [degraded] mode because its index or del file
+/// could not be written
+///
+/// [degraded]: crate::controller::Controller::is_degraded
+#[derive(Debug, Clone)]
+pub struct DegradedError;
+
+impl Display for DegradedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "degraded: the database is read-only since its index or del file could not be written"
+        )
+    }
+}
+
+impl Error for DegradedError {}
+
+impl From<DegradedError> for io::Error {
+    fn from(err: DegradedError) -> Self {
+        io::Error::new(ErrorKind::PermissionDenied, err)
+    }
+}
+
+/// Error thrown when loading a database whose values cannot be decrypted with the encryption key
+/// it is connected with
+#[derive(Debug, Clone)]
+pub struct WrongEncryptionKeyError;
+
+impl Display for WrongEncryptionKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "wrong encryption key: the saved values cannot be decrypted with the encryption key"
+        )
+    }
+}
+
+impl Error for WrongEncryptionKeyError {}
+
+impl From<WrongEncryptionKeyError> for io::Error {
+    fn from(err: WrongEncryptionKeyError) -> Self {
+        io::Error::new(ErrorKind::InvalidInput, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_convert_into_io_errors_of_matching_kind() {
+        assert_io_error_from(NotFoundError, ErrorKind::NotFound);
+        assert_io_error_from(CorruptedDataError, ErrorKind::InvalidData);
+        assert_io_error_from(AlreadyRunningError, ErrorKind::Other);
+        assert_io_error_from(NotRunningError, ErrorKind::Other);
+        assert_io_error_from(NotADatabaseError, ErrorKind::InvalidInput);
+        assert_io_error_from(NotAnIntegerError, ErrorKind::InvalidData);
+        assert_io_error_from(InvalidKeyError, ErrorKind::InvalidInput);
+        assert_io_error_from(ValueTooLargeError, ErrorKind::InvalidInput);
+        assert_io_error_from(NotTrackedError, ErrorKind::Unsupported);
+        assert_io_error_from(
+            VerificationError {
+                inconsistency: Inconsistency::UnreadableFile {
+                    file_name: "1000.cky".to_string(),
+                },
+            },
+            ErrorKind::InvalidData,
+        );
+        assert_io_error_from(
+            RollHookError {
+                data_file: "1000.cky".to_string(),
+                message: "upload failed".to_string(),
+            },
+            ErrorKind::Other,
+        );
+        assert_io_error_from(InvalidVacuumIntervalError, ErrorKind::InvalidInput);
+        assert_io_error_from(
+            AlreadyExistsError {
+                key: "cow".to_string(),
+            },
+            ErrorKind::AlreadyExists,
+        );
+        assert_io_error_from(DegradedError, ErrorKind::PermissionDenied);
+        assert_io_error_from(SymlinkedDbPathError, ErrorKind::InvalidInput);
+        assert_io_error_from(WrongEncryptionKeyError, ErrorKind::InvalidInput);
+    }
+
+    /// Asserts that converting `err` into an [io::Error] gives an error of the given `kind`
+    /// from which the original error can be got back
+    fn assert_io_error_from<E: Error + Clone + Send + Sync + 'static>(err: E, kind: ErrorKind)
+    where
+        io::Error: From<E>,
+    {
+        let io_err = io::Error::from(err.clone());
+
+        assert_eq!(kind, io_err.kind());
+        assert_eq!(err.to_string(), io_err.to_string());
+        assert!(io_err
+            .into_inner()
+            .expect("inner error")
+            .downcast::<E>()
+            .is_ok());
+    }
+}
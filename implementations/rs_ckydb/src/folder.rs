@@ -0,0 +1,373 @@
+use crate::utils;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::{ffi::OsStr, fs};
+
+/// `Folder` is the place where the database files are kept.
+///
+/// It is either the [Disk] folder at the database path, or a [Memory] folder, a map of
+/// file names to file contents, used when the database is in in-memory-only mode.
+/// Both expose the same file operations so that the store behaves the same way in both modes.
+///
+/// [Disk]: Folder::Disk
+/// [Memory]: Folder::Memory
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Folder {
+    Disk,
+    Memory(HashMap<String, String>),
+}
+
+impl Folder {
+    /// Creates the folder at `path` if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// See [fs::create_dir_all]
+    // #[inline]
+    pub(crate) fn create_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::create_dir_all(path),
+            Folder::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Deletes the folder at `path` and all files in it
+    ///
+    /// # Errors
+    ///
+    /// See [fs::remove_dir_all]
+    // #[inline]
+    pub(crate) fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::remove_dir_all(path),
+            Folder::Memory(files) => {
+                files.clear();
+                Ok(())
+            }
+        }
+    }
+
+    /// Deletes the file at `path`
+    ///
+    /// # Errors
+    ///
+    /// See [fs::remove_file]
+    // #[inline]
+    pub(crate) fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::remove_file(path),
+            Folder::Memory(files) => files
+                .remove(&get_file_name(&path))
+                .and(Some(()))
+                .ok_or_else(|| io::Error::from(ErrorKind::NotFound)),
+        }
+    }
+
+    /// Empties the folder at `path` immediately by moving it to `trash_path`, and then deletes
+    /// `trash_path` and all files in it in a background task whose handle is returned
+    ///
+    /// # Errors
+    ///
+    /// See [fs::rename]
+    // #[inline]
+    pub(crate) fn remove_dir_all_in_background<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        trash_path: PathBuf,
+    ) -> io::Result<JoinHandle<io::Result<()>>> {
+        match self {
+            Folder::Disk => {
+                fs::rename(path, &trash_path)?;
+                Ok(thread::spawn(move || fs::remove_dir_all(trash_path)))
+            }
+            Folder::Memory(files) => {
+                files.clear();
+                Ok(thread::spawn(|| Ok(())))
+            }
+        }
+    }
+
+    /// Checks whether there is a file at `path`
+    // #[inline]
+    pub(crate) fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        match self {
+            Folder::Disk => path.as_ref().is_file(),
+            Folder::Memory(files) => files.contains_key(&get_file_name(&path)),
+        }
+    }
+
+    /// Checks whether `path` is a symbolic link, whatever it points to
+    // #[inline]
+    pub(crate) fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool {
+        match self {
+            Folder::Disk => path.as_ref().is_symlink(),
+            Folder::Memory(_) => false,
+        }
+    }
+
+    /// Creates a given file if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// See [utils::create_file_if_not_exist]
+    // #[inline]
+    pub(crate) fn create_file_if_not_exist<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self {
+            Folder::Disk => utils::create_file_if_not_exist(path),
+            Folder::Memory(files) => {
+                files.entry(get_file_name(&path)).or_default();
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads the entire contents of the file at `path` into a string
+    ///
+    /// # Errors
+    ///
+    /// See [fs::read_to_string]
+    // #[inline]
+    pub(crate) fn read_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        match self {
+            Folder::Disk => fs::read_to_string(path),
+            Folder::Memory(files) => files
+                .get(&get_file_name(&path))
+                .cloned()
+                .ok_or_else(|| io::Error::from(ErrorKind::NotFound)),
+        }
+    }
+
+    /// Overwrites the file at `path` with the given `content`, creating it if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// See [fs::write]
+    // #[inline]
+    pub(crate) fn write<P: AsRef<Path>>(&mut self, path: P, content: &str) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::write(path, content),
+            Folder::Memory(files) => {
+                files.insert(get_file_name(&path), content.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Appends the supplied content to the file
+    ///
+    /// # Errors
+    ///
+    /// See [utils::append_to_file]
+    // #[inline]
+    pub(crate) fn append_to_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &str,
+    ) -> io::Result<()> {
+        match self {
+            Folder::Disk => utils::append_to_file(path, content),
+            Folder::Memory(files) => files
+                .get_mut(&get_file_name(&path))
+                .map(|file| file.push_str(content))
+                .ok_or_else(|| io::Error::from(ErrorKind::NotFound)),
+        }
+    }
+
+    /// Renames the file at `from` to `to`
+    ///
+    /// # Errors
+    ///
+    /// See [fs::rename]
+    // #[inline]
+    pub(crate) fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        from: P,
+        to: Q,
+    ) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::rename(from, to),
+            Folder::Memory(files) => {
+                let content = files
+                    .remove(&get_file_name(&from))
+                    .ok_or_else(|| io::Error::from(ErrorKind::NotFound))?;
+                files.insert(get_file_name(&to), content);
+                Ok(())
+            }
+        }
+    }
+
+    /// Flushes all the data written to the file at `path` to the physical disk
+    ///
+    /// # Errors
+    ///
+    /// See [fs::File::open] and [fs::File::sync_all]
+    // #[inline]
+    pub(crate) fn sync<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        match self {
+            Folder::Disk => fs::File::open(path)?.sync_all(),
+            Folder::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Gets all the names of the files in the folder at `path`
+    ///
+    /// # Errors
+    ///
+    /// See [utils::get_file_names_in_folder]
+    // #[inline]
+    pub(crate) fn get_file_names_in_folder<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Vec<String>> {
+        match self {
+            Folder::Disk => utils::get_file_names_in_folder(path),
+            Folder::Memory(files) => Ok(files.keys().cloned().collect()),
+        }
+    }
+
+    /// Retrieves all files in the folder at `path` with the given extensions `exts`
+    ///
+    /// # Errors
+    ///
+    /// See [utils::get_files_with_extensions]
+    // #[inline]
+    pub(crate) fn get_files_with_extensions<P: AsRef<Path>>(
+        &self,
+        path: P,
+        exts: Vec<&str>,
+    ) -> io::Result<Vec<String>> {
+        match self {
+            Folder::Disk => utils::get_files_with_extensions(path, exts),
+            Folder::Memory(files) => Ok(files
+                .keys()
+                .filter(|name| {
+                    let ext = Path::new(name).extension().and_then(OsStr::to_str);
+                    ext.is_some_and(|ext| exts.contains(&ext))
+                })
+                .cloned()
+                .collect()),
+        }
+    }
+
+    /// Returns the size of the file at the given `path` in kilobytes
+    ///
+    /// # Errors
+    ///
+    /// See [utils::get_file_size]
+    // #[inline]
+    pub(crate) fn get_file_size<P: AsRef<Path>>(&self, path: P) -> io::Result<f64> {
+        match self {
+            Folder::Disk => utils::get_file_size(path),
+            Folder::Memory(_) => Ok(self.read_to_string(path)?.len() as f64 / 1024.0),
+        }
+    }
+
+    /// Returns the size of the file at the given `path` in bytes
+    ///
+    /// # Errors
+    ///
+    /// See [fs::metadata]
+    // #[inline]
+    pub(crate) fn get_file_size_bytes<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        match self {
+            Folder::Disk => Ok(fs::metadata(path)?.len()),
+            Folder::Memory(_) => Ok(self.read_to_string(path)?.len() as u64),
+        }
+    }
+
+    /// Deletes the key values corresponding to the `keys_to_delete`
+    /// if those keys exist in the file at `path`
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::read_to_string] and [Folder::write]
+    // #[inline]
+    pub(crate) fn delete_key_values_from_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        keys_to_delete: &Vec<String>,
+    ) -> io::Result<()> {
+        match self {
+            Folder::Disk => utils::delete_key_values_from_file(path, keys_to_delete),
+            Folder::Memory(_) => {
+                let content = self.read_to_string(&path)?;
+                self.write(
+                    path,
+                    &utils::delete_key_values_from_str(&content, keys_to_delete),
+                )
+            }
+        }
+    }
+
+    /// Deletes the key values corresponding to the `keys_to_delete` from each of the files
+    /// at `paths`. Files on disk are independent of each other so they are rewritten
+    /// in parallel, spread over as many threads as there are CPUs
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::delete_key_values_from_file]
+    pub(crate) fn delete_key_values_from_files(
+        &mut self,
+        paths: &[PathBuf],
+        keys_to_delete: &Vec<String>,
+    ) -> io::Result<()> {
+        if let Folder::Memory(_) = self {
+            return paths
+                .iter()
+                .try_for_each(|path| self.delete_key_values_from_file(path, keys_to_delete));
+        }
+
+        let number_of_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = paths.len().div_ceil(number_of_threads).max(1);
+
+        thread::scope(|scope| {
+            let tasks: Vec<_> = paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk.iter().try_for_each(|path| {
+                            utils::delete_key_values_from_file(path, keys_to_delete)
+                        })
+                    })
+                })
+                .collect();
+
+            // all tasks are joined, even after an error, so that every file gets vacuumed
+            let results: Vec<io::Result<()>> = tasks
+                .into_iter()
+                .map(|task| task.join().expect("join vacuum task"))
+                .collect();
+            results.into_iter().collect()
+        })
+    }
+
+    /// Overwrites the data in the file at `path` with the
+    /// equivalent of the map data passed
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::write]
+    // #[inline]
+    pub(crate) fn persist_map_data_to_file<P: AsRef<Path>>(
+        &mut self,
+        data: &HashMap<String, String>,
+        path: P,
+    ) -> io::Result<()> {
+        match self {
+            Folder::Disk => utils::write_key_values_to_file(path, data),
+            Folder::Memory(_) => self.write(path, &utils::convert_key_values_to_str(data)),
+        }
+    }
+}
+
+/// Gets the name of the file at the given `path`, used as the key for files in a [Folder::Memory]
+// #[inline]
+fn get_file_name<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or("")
+        .to_string()
+}
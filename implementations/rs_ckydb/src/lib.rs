@@ -1,10 +1,21 @@
 extern crate core;
 
 mod cache;
+mod clock;
 mod constants;
 mod controller;
-mod errors;
+#[cfg(feature = "encryption")]
+mod crypto;
+pub mod errors;
+mod folder;
+mod options;
+mod snapshot;
 mod store;
 mod utils;
 
-pub use controller::{connect, Controller};
+pub use controller::{connect, connect_path, connect_with_options, Ckydb, Controller};
+pub use options::{DbConfig, FsyncPolicy, LoadProgressHook, Options, RetryPolicy, RollHook};
+pub use snapshot::Snapshot;
+pub use store::{
+    Inconsistency, KeyLocationCounts, KeyStatus, MergePolicy, Stats, Storage, VerifyReport,
+};
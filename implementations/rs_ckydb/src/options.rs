@@ -0,0 +1,297 @@
+use crate::constants::{KEY_VALUE_SEPARATOR, TOKEN_SEPARATOR};
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `Options` holds the optional settings with which the database can be [connected] to.
+/// Use [Options::default] for the default settings and override only the fields needed.
+///
+/// [connected]: crate::controller::connect_with_options
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Whether the keys marked for deletion should be vacuumed when the database is loaded.
+    /// Turning this off makes loading faster for databases with big del files, leaving the
+    /// vacuuming to the background task. Default: `true`
+    pub vacuum_on_load: bool,
+    /// Whether connecting should fail if the database folder is not already a ckydb database
+    /// i.e. it has no index file or del file, instead of initializing a new database in it.
+    /// This guards against accidentally pointing at the wrong folder. Default: `false`
+    pub from_existing: bool,
+    /// Whether connecting should fail if the database folder is a symbolic link to another folder.
+    /// Operations like [clear] act on the database folder path, so a symbolic link would be
+    /// replaced by a fresh folder, leaving the data in the folder it pointed to behind.
+    /// This guards against such surprises. Default: `false`
+    ///
+    /// [clear]: crate::controller::Controller::clear
+    pub refuse_symlinked_db_path: bool,
+    /// Whether the database should be [verified] right after it is loaded on connecting,
+    /// failing to connect if any inconsistency is found instead of finding corrupted data
+    /// lazily on a later read. This makes connecting slower as all data files are read.
+    /// Default: `false`
+    ///
+    /// [verified]: crate::controller::Controller::verify
+    pub verify_on_connect: bool,
+    /// Whether the database should be kept purely in memory, without creating or writing any files
+    /// on disk. All data is lost once the database is dropped. This is useful for tests and
+    /// ephemeral caches. Default: `false`
+    pub in_memory: bool,
+    /// When the files written on every [set] should be flushed to the physical disk, trading
+    /// write throughput for durability in case of an OS crash or power loss. Default: [FsyncPolicy::Never]
+    ///
+    /// [set]: crate::controller::Controller::set
+    pub fsync: FsyncPolicy,
+    /// The maximum size in bytes of any value that can be [set]. Bigger values are rejected
+    /// before anything is written, so that a single giant value cannot force an immediate log file
+    /// roll or exhaust memory when its data file is loaded into the cache.
+    /// `None` means there is no limit. Default: `None`
+    ///
+    /// [set]: crate::controller::Controller::set
+    pub max_value_size_bytes: Option<usize>,
+    /// Whether a data file that grows beyond `max_file_size_kb` as old keys are updated
+    /// should be split into two data files. Without this, only the log file is rolled when it
+    /// gets too big, and data files can grow without bound from large updates. Default: `false`
+    pub split_data_files: bool,
+    /// Whether all data files should be scanned for a key whose value is not found in the data
+    /// file it is expected in e.g. because the data files were renamed, before an error is returned.
+    /// This trades the speed of such reads for resilience. Default: `false`
+    pub resilient_reads: bool,
+    /// The maximum memory in kilobytes that the cache of data file contents may take up,
+    /// independent of `max_file_size_kb`. Within it, the contents of many recently read data files
+    /// are cached, evicting the least recently used first. A data file whose contents alone
+    /// exceed it is read from disk on every access. `None` means only the contents of the most
+    /// recently read data file are cached, whatever their size. Default: `None`
+    pub cache_memory_budget_kb: Option<f64>,
+    /// The size in kilobytes below which adjacent data files are merged into one when vacuuming,
+    /// as long as the merged data file stays smaller than `max_file_size_kb`. This keeps the
+    /// number of data files down when many small log files have been rolled.
+    /// `None` means data files are never merged. Default: `None`
+    pub min_data_file_kb: Option<f64>,
+    /// The number of [set]s to buffer in memory before the log file is rewritten, instead of
+    /// rewriting it on every set. This trades durability for write throughput, as buffered
+    /// sets are lost if the process crashes before they are [flushed]. The buffer is also flushed
+    /// on [close]. `None` means the log file is rewritten on every set. Default: `None`
+    ///
+    /// [set]: crate::controller::Controller::set
+    /// [flushed]: crate::controller::Controller::flush
+    /// [close]: crate::controller::Controller::close
+    pub flush_every_n: Option<usize>,
+    /// Whether the time at which each key was last [set] should be recorded alongside its value,
+    /// so that it can be queried with [last_modified] e.g. for syncing or cache invalidation.
+    /// It should be set when the database is created and not changed afterwards, since values
+    /// saved with it on are read back wrongly with it off. Default: `false`
+    ///
+    /// [set]: crate::controller::Controller::set
+    /// [last_modified]: crate::controller::Controller::last_modified
+    pub track_last_modified: bool,
+    /// How writes to the database files that fail with transient I/O errors e.g. on networked
+    /// filesystems should be retried before the error is returned. Writes interrupted by
+    /// a signal are always retried. `None` means no other errors are retried. Default: `None`
+    pub retry_policy: Option<RetryPolicy>,
+    /// The 256-bit key with which every value is encrypted with AES-GCM before it is written
+    /// to disk, and decrypted when it is read back. The keys themselves are saved in the clear
+    /// so that they can be looked up. It should be set when the database is created and not
+    /// changed afterwards, since values saved with one key cannot be read with another.
+    /// `None` means values are saved in the clear. Default: `None`
+    #[cfg(feature = "encryption")]
+    pub encryption_key: Option<[u8; 32]>,
+    /// The maximum number of entries the log file can hold before it is rolled into a data file,
+    /// even if it has not yet reached `max_file_size_kb`. This is useful for workloads with
+    /// very many tiny values. `None` means there is no limit. Default: `None`
+    pub max_log_entries: Option<usize>,
+    /// The hook to run every time the log file is rolled into a new data file, with the name
+    /// of that data file e.g. to replicate, compress or upload the newly sealed file.
+    /// If the hook fails, the operation that rolled the log file returns a [RollHookError]
+    /// wrapped in an [io::Error], though the log file will have been rolled. Default: `None`
+    ///
+    /// [RollHookError]: crate::errors::RollHookError
+    pub roll_hook: Option<RollHook>,
+    /// The hook to run with the number of files processed so far and the total number of files
+    /// as the database files are loaded, on connecting and on every [reload] e.g. to show
+    /// a progress bar when loading large databases. Default: `None`
+    ///
+    /// [reload]: crate::controller::Controller::reload
+    pub load_progress_hook: Option<LoadProgressHook>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            vacuum_on_load: true,
+            from_existing: false,
+            refuse_symlinked_db_path: false,
+            verify_on_connect: false,
+            in_memory: false,
+            fsync: FsyncPolicy::Never,
+            max_value_size_bytes: None,
+            split_data_files: false,
+            resilient_reads: false,
+            cache_memory_budget_kb: None,
+            min_data_file_kb: None,
+            flush_every_n: None,
+            track_last_modified: false,
+            retry_policy: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            max_log_entries: None,
+            roll_hook: None,
+            load_progress_hook: None,
+        }
+    }
+}
+
+/// `FsyncPolicy` determines when the database files are flushed to the physical disk.
+///
+/// Without an explicit flush, data written to a file may sit in the operating system's buffers
+/// for a while, and be lost if the OS crashes, even though the write returned `Ok`.
+/// Flushing more often makes the data more durable but makes writes much slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Never flush explicitly, leaving it to the operating system. This is the fastest
+    /// but recently set values can be lost on an OS crash.
+    Never,
+    /// Flush the log file (or data file) and the index file after every [set].
+    /// This is the most durable but the slowest.
+    ///
+    /// [set]: crate::controller::Controller::set
+    OnEverySet,
+    /// Flush only when the log file is rolled into a data file. Values in the current
+    /// log file can be lost on an OS crash but those in data files are durable.
+    OnRoll,
+}
+
+/// `RetryPolicy` determines how often, and how patiently, writes that fail with transient
+/// I/O errors i.e. of kind [WouldBlock] or [TimedOut] are retried.
+///
+/// The wait before each retry starts at `backoff` and doubles with every retry.
+///
+/// [WouldBlock]: std::io::ErrorKind::WouldBlock
+/// [TimedOut]: std::io::ErrorKind::TimedOut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts of a write, including the first one
+    pub max_attempts: u32,
+    /// The time to wait before the first retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Initializes a new RetryPolicy with the given maximum number of attempts and initial backoff
+    // #[inline]
+    pub fn new(max_attempts: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff,
+        }
+    }
+}
+
+/// `RollHook` is a callback run every time the log file is rolled into a data file,
+/// with the name of the new data file. An error it returns is surfaced as a [RollHookError]
+/// wrapped in an [io::Error]. Clones of a hook share the same callback
+///
+/// [RollHookError]: crate::errors::RollHookError
+#[derive(Clone)]
+pub struct RollHook(Arc<Mutex<RollHookFn>>);
+
+/// The callback wrapped by a [RollHook]
+type RollHookFn = dyn FnMut(&str) -> io::Result<()> + Send;
+
+impl RollHook {
+    /// Initializes a new RollHook that runs the given `hook`
+    pub fn new<F: FnMut(&str) -> io::Result<()> + Send + 'static>(hook: F) -> RollHook {
+        RollHook(Arc::new(Mutex::new(hook)))
+    }
+
+    /// Runs the hook with the name of the new `data_file`
+    ///
+    /// # Errors
+    ///
+    /// Returns the error returned by the hook
+    pub(crate) fn call(&self, data_file: &str) -> io::Result<()> {
+        (self.0.lock().expect("lock roll hook"))(data_file)
+    }
+}
+
+impl Debug for RollHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RollHook")
+    }
+}
+
+impl PartialEq for RollHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// `LoadProgressHook` is a callback run as the files in the database folder are loaded,
+/// with the number of files processed so far and the total number of files.
+/// The index file is counted last, once it has been loaded. Clones of a hook share
+/// the same callback
+#[derive(Clone)]
+pub struct LoadProgressHook(Arc<Mutex<dyn FnMut(usize, usize) + Send>>);
+
+impl LoadProgressHook {
+    /// Initializes a new LoadProgressHook that runs the given `hook`
+    pub fn new<F: FnMut(usize, usize) + Send + 'static>(hook: F) -> LoadProgressHook {
+        LoadProgressHook(Arc::new(Mutex::new(hook)))
+    }
+
+    /// Runs the hook with the number of files processed so far and the total number of files
+    pub(crate) fn call(&self, files_processed: usize, total_files: usize) {
+        (self.0.lock().expect("lock load progress hook"))(files_processed, total_files)
+    }
+}
+
+impl Debug for LoadProgressHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LoadProgressHook")
+    }
+}
+
+impl PartialEq for LoadProgressHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// `DbConfig` is the configuration with which a database was [connected] to
+///
+/// [connected]: crate::controller::connect_with_options
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbConfig {
+    /// The path to the database folder
+    pub db_path: PathBuf,
+    /// The maximum size in kilobytes of the log file before it is rolled into a data file
+    pub max_file_size_kb: f64,
+    /// The time in seconds between vacuuming cycles
+    pub vacuum_interval_sec: f64,
+    /// The options the database was connected with
+    pub options: Options,
+    /// The separator between each key and its value in the database files
+    pub key_value_separator: &'static str,
+    /// The separator between the entries in the database files
+    pub token_separator: &'static str,
+}
+
+impl DbConfig {
+    /// Initializes a new DbConfig with the given settings and the separators in use
+    // #[inline]
+    pub fn new(
+        db_path: &Path,
+        max_file_size_kb: f64,
+        vacuum_interval_sec: f64,
+        options: &Options,
+    ) -> DbConfig {
+        DbConfig {
+            db_path: db_path.to_path_buf(),
+            max_file_size_kb,
+            vacuum_interval_sec,
+            options: options.clone(),
+            key_value_separator: KEY_VALUE_SEPARATOR,
+            token_separator: TOKEN_SEPARATOR,
+        }
+    }
+}
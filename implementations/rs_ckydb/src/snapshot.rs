@@ -0,0 +1,265 @@
+use crate::constants::DATA_FILE_EXT;
+#[cfg(feature = "encryption")]
+use crate::crypto;
+use crate::errors::NotFoundError;
+use crate::folder::Folder;
+use crate::utils;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+
+/// The copies of the contents of the data files a [Snapshot] reads from, keyed by the data file
+/// names, taken by the store just before it rewrites or removes the data files.
+/// `None` means the data file has not been changed since the snapshot was taken
+pub(crate) type DataFileCopies = Mutex<HashMap<String, Option<String>>>;
+
+/// `Snapshot` is a read-only view of the database as it was at the moment it was taken.
+///
+/// The keys, and the values still in the log file at that moment, are copied into the snapshot,
+/// so they are unaffected by later writes. The values in the data files are read lazily from disk.
+/// Before a data file is rewritten or removed e.g. when old keys are updated, when deleted keys
+/// are vacuumed or when the database is cleared, its contents are copied into the snapshots
+/// that read from it, so they keep seeing the values as they were. Only changes made through
+/// the same database are guarded against, not those made to its files by other processes.
+/// For an in-memory database, the whole database is copied so the snapshot is fully isolated.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    db_path: PathBuf,
+    folder: Folder,
+    index: HashMap<String, String>,
+    memtable: HashMap<String, String>,
+    data_files: Vec<String>,
+    data_file_copies: Arc<DataFileCopies>,
+    current_log_file: String,
+    track_last_modified: bool,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<[u8; 32]>,
+}
+
+impl Snapshot {
+    /// Initializes a new Snapshot from copies of the store's properties
+    // #[inline]
+    pub(crate) fn new(
+        db_path: PathBuf,
+        folder: Folder,
+        index: HashMap<String, String>,
+        memtable: HashMap<String, String>,
+        data_files: Vec<String>,
+        current_log_file: String,
+        track_last_modified: bool,
+    ) -> Snapshot {
+        let data_file_copies = data_files
+            .iter()
+            .map(|data_file| (data_file.clone(), None))
+            .collect();
+
+        Snapshot {
+            db_path,
+            folder,
+            index,
+            memtable,
+            data_files,
+            data_file_copies: Arc::new(Mutex::new(data_file_copies)),
+            current_log_file,
+            track_last_modified,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+        }
+    }
+
+    /// Initializes a new Snapshot holding copies of the given key-value `pairs` and nothing else,
+    /// so that it is fully isolated. The keys are taken to have been created in the order
+    /// of the `pairs`
+    pub fn from_pairs(pairs: Vec<(String, String)>) -> Snapshot {
+        let mut index = HashMap::with_capacity(pairs.len());
+        let mut memtable = HashMap::with_capacity(pairs.len());
+        for (i, (key, value)) in pairs.into_iter().enumerate() {
+            let timestamped_key = utils::format_timestamp(i as u128);
+            index.insert(key, timestamped_key.clone());
+            memtable.insert(timestamped_key, value);
+        }
+
+        Snapshot::new(
+            PathBuf::new(),
+            Folder::Memory(HashMap::new()),
+            index,
+            memtable,
+            vec![],
+            "".to_string(),
+            false,
+        )
+    }
+
+    /// Sets the key with which the values in the snapshot are decrypted
+    // #[inline]
+    #[cfg(feature = "encryption")]
+    pub(crate) fn with_encryption_key(mut self, encryption_key: Option<[u8; 32]>) -> Snapshot {
+        self.encryption_key = encryption_key;
+        self
+    }
+
+    /// Gets a reference to the copies of the data files the snapshot reads from, through which
+    /// the store copies the data files into the snapshot for as long as the snapshot is alive
+    // #[inline]
+    pub(crate) fn get_data_file_copies(&self) -> Weak<DataFileCopies> {
+        Arc::downgrade(&self.data_file_copies)
+    }
+
+    /// Retrieves the value corresponding to the given key as it was when the snapshot was taken
+    ///
+    /// # Errors
+    /// - [NotFoundError] in case the key was not in the database when the snapshot was taken,
+    ///   or its value is no longer in its data file, or cannot be decrypted
+    ///
+    /// [NotFoundError]: crate::errors::NotFoundError
+    pub fn get(&self, key: &str) -> Result<String, NotFoundError> {
+        let stored_value = self.get_stored_value(key)?;
+        let value = if self.track_last_modified {
+            utils::decode_value_with_modified_timestamp(&stored_value).1
+        } else {
+            &stored_value
+        };
+
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            return crypto::decrypt_value(value, key).or(Err(NotFoundError));
+        }
+
+        Ok(value.to_string())
+    }
+
+    /// Retrieves the value corresponding to the given key as it was saved on disk,
+    /// including any last-modified timestamp
+    ///
+    /// # Errors
+    /// - [NotFoundError] in case the key was not in the database when the snapshot was taken,
+    ///   or its value is no longer in its data file
+    fn get_stored_value(&self, key: &str) -> Result<String, NotFoundError> {
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?;
+
+        let is_in_log_file = self.memtable.contains_key(timestamped_key)
+            || utils::compare_timestamps(timestamped_key, &self.current_log_file).is_ge();
+
+        if is_in_log_file {
+            return self
+                .memtable
+                .get(timestamped_key)
+                .cloned()
+                .ok_or(NotFoundError);
+        }
+
+        let (start, _) = utils::get_timestamp_range_for_key(
+            &self.data_files,
+            &self.current_log_file,
+            timestamped_key,
+        )
+        .ok_or(NotFoundError)?;
+        // the copies are locked while the data file is read so that it is not changed meanwhile
+        let data_file_copies = self.data_file_copies.lock().expect("lock data file copies");
+        let content = match data_file_copies.get(&start) {
+            Some(Some(content)) => content.clone(),
+            _ => {
+                let file_path = self.db_path.join(format!("{}.{}", start, DATA_FILE_EXT));
+                self.folder
+                    .read_to_string(file_path)
+                    .or(Err(NotFoundError))?
+            }
+        };
+        let data = utils::extract_key_values_from_str(&content).or(Err(NotFoundError))?;
+
+        data.get(timestamped_key).cloned().ok_or(NotFoundError)
+    }
+
+    /// Returns all the keys that were in the database when the snapshot was taken,
+    /// in the order in which they were created
+    pub fn keys(&self) -> Vec<String> {
+        let mut entries: Vec<(&String, &String)> = self
+            .index
+            .iter()
+            .map(|(key, timestamped_key)| (timestamped_key, key))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        entries.into_iter().map(|(_, key)| key.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{Storage, Store};
+    use serial_test::serial;
+
+    const DB_PATH: &str = "test_snapshot_db";
+    const MAX_FILE_SIZE_KB: f64 = 320.0 / 1024.0;
+
+    #[test]
+    #[serial]
+    fn snapshot_is_not_affected_by_later_sets_and_deletes() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let snapshot = store.snapshot();
+
+        store.delete("cow").expect("delete cow");
+        store.set("goat", "1 month").expect("update goat");
+        store.set("sheep", "2 months").expect("set sheep");
+
+        assert_eq!("500 months", snapshot.get("cow").unwrap());
+        assert_eq!("23 months", snapshot.get("dog").unwrap());
+        assert_eq!("678 months", snapshot.get("goat").unwrap());
+        assert!(snapshot.get("sheep").is_err());
+        assert_eq!(
+            vec!["cow", "dog", "fish", "hen", "goat", "pig"],
+            snapshot.keys()
+        );
+
+        assert!(store.get("cow").is_err());
+        assert_eq!("1 month", store.get("goat").unwrap());
+        assert_eq!("2 months", store.get("sheep").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn snapshot_is_not_affected_by_later_rewrites_of_data_files() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let snapshot = store.snapshot();
+
+        // cow and dog are in the same data file, which is rewritten by each of these
+        store.set("cow", "501 months").expect("update cow");
+        assert_eq!("500 months", snapshot.get("cow").unwrap());
+        store.delete("dog").expect("delete dog");
+        store.vacuum().expect("vacuum");
+        assert_eq!("23 months", snapshot.get("dog").unwrap());
+
+        let later_snapshot = store.snapshot();
+        store.clear().expect("clear");
+
+        assert_eq!("500 months", snapshot.get("cow").unwrap());
+        assert_eq!("23 months", snapshot.get("dog").unwrap());
+        assert_eq!("501 months", later_snapshot.get("cow").unwrap());
+        assert!(later_snapshot.get("dog").is_err());
+        assert!(store.get("cow").is_err());
+    }
+
+    #[test]
+    fn snapshot_from_pairs_holds_only_those_pairs_in_order() {
+        let snapshot = Snapshot::from_pairs(vec![
+            ("goat".to_string(), "678 months".to_string()),
+            ("cow".to_string(), "500 months".to_string()),
+        ]);
+
+        assert_eq!("678 months", snapshot.get("goat").unwrap());
+        assert_eq!("500 months", snapshot.get("cow").unwrap());
+        assert!(snapshot.get("dog").is_err());
+        assert_eq!(vec!["goat", "cow"], snapshot.keys());
+    }
+}
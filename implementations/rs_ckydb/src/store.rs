@@ -1,30 +1,57 @@
 use crate::cache::{Cache, Caching};
+use crate::clock::{Clock, SystemClock};
 use crate::constants::{
     DATA_FILE_EXT, DEL_FILENAME, INDEX_FILENAME, KEY_VALUE_SEPARATOR, LOG_FILE_EXT, TOKEN_SEPARATOR,
 };
-use crate::errors::{CorruptedDataError, NotFoundError};
+#[cfg(feature = "encryption")]
+use crate::crypto;
+#[cfg(feature = "encryption")]
+use crate::errors::WrongEncryptionKeyError;
+use crate::errors::{
+    AlreadyExistsError, CorruptedDataError, DegradedError, InvalidKeyError, NotADatabaseError,
+    NotFoundError, NotTrackedError, RollHookError, ValueTooLargeError,
+};
+use crate::folder::Folder;
+use crate::options::{FsyncPolicy, LoadProgressHook, RetryPolicy, RollHook};
+use crate::snapshot::{DataFileCopies, Snapshot};
 use crate::utils;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::sync::Weak;
+use std::thread::{self, JoinHandle};
 
 /// `Store` trait represents the basic expectation for the internal store that accesses the file
 /// system as well as stores data in memory
 ///
 /// It must be able to do the basic [set], [get], [delete], and [clear] operations for
-/// accessing and manipulating data in the database.
+/// accessing and manipulating data in the database, to [filter] all its key-value pairs
+/// and to take a [snapshot] of them.
 ///
-/// It must also be able to [load] the data from disk into memory, e.g. at start up
+/// It must also be able to [load] the data from disk into memory, e.g. at start up.
 /// It should also be able to [vacuum] any keys that have been marked for deletion and are
 /// thus no longer accessible
 ///
+/// All the other methods have default implementations built on these, which backends
+/// can override with more efficient or more complete ones
+///
 /// [set]: Storage::set
 /// [get]: Storage::get
 /// [delete]: Storage::delete
 /// [clear]: Storage::clear
+/// [filter]: Storage::filter
+/// [snapshot]: Storage::snapshot
 /// [load]: Storage::load
 /// [vacuum]: Storage::vacuum
-pub(crate) trait Storage {
+///
+/// It is object-safe so that a [Ckydb] can run over any `Box<dyn Storage>`,
+/// not just the file-based store e.g. see [Ckydb::with_storage]
+///
+/// [Ckydb]: crate::controller::Ckydb
+/// [Ckydb::with_storage]: crate::controller::Ckydb::with_storage
+pub trait Storage {
     /// Loads the storage from disk
     ///
     /// # Errors
@@ -34,883 +61,5389 @@ pub(crate) trait Storage {
     /// [io::Error]: std::io::Error
     fn load(&mut self) -> io::Result<()>;
 
-    /// Adds or updates the value corresponding to the given key in store
+    /// Reloads the in-memory props from the data currently on disk without clearing
+    /// anything on disk, e.g. to pick up changes made by another process or a completed vacuum
+    ///
+    /// By default, the storage is simply [loaded](Storage::load) again
     ///
     /// # Errors
-    /// - [CorruptedDataError] in case the data on disk is inconsistent with that in memory
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
-    /// [CorruptedDataError]: crate::errors::CorruptedDataError
-    fn set(&mut self, key: &str, value: &str) -> Result<(), CorruptedDataError>;
+    /// [io::Error]: std::io::Error
+    fn reload(&mut self) -> io::Result<()> {
+        self.load()
+    }
 
-    /// Retrieves the value corresponding to the given key
+    /// Adds or updates the value corresponding to the given key in store
     ///
     /// # Errors
-    /// - [NotFoundError] in case the key is not found in the store
-    /// - Panics with [CorruptedDataError] in case the data on disk is not
-    /// consistent with that in memory
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case the key is empty
+    /// - [ValueTooLargeError] wrapped in an [io::Error] in case the value is bigger than the
+    ///   maximum value size
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
     ///
-    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [io::Error]: std::io::Error
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    /// [ValueTooLargeError]: crate::errors::ValueTooLargeError
     /// [CorruptedDataError]: crate::errors::CorruptedDataError
-    fn get(&mut self, key: &str) -> Result<String, NotFoundError>;
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()>;
 
-    /// Removes the key-value pair corresponding to the passed key
+    /// Adds the given key and value to the store only if the key is not already in it.
+    /// Unlike `set`, it never overwrites an existing value, which makes it suitable
+    /// for allocating unique ids
+    ///
+    /// By default, the key is looked up with [Storage::get] before it is [set](Storage::set)
     ///
     /// # Errors
-    /// - [NotFoundError] in case the key is not found in the store
+    /// - [AlreadyExistsError] wrapped in an [io::Error] in case the key is already in the store
+    /// - See `set` for the errors that can occur on saving the key-value pair
     ///
-    /// [NotFoundError]: crate::errors::NotFoundError
-    fn delete(&mut self, key: &str) -> Result<(), NotFoundError>;
+    /// [io::Error]: std::io::Error
+    /// [AlreadyExistsError]: crate::errors::AlreadyExistsError
+    fn insert_new(&mut self, key: &str, value: &str) -> io::Result<()> {
+        if self.get(key).is_ok() {
+            return Err(AlreadyExistsError {
+                key: key.to_string(),
+            }
+            .into());
+        }
 
-    /// Resets the entire Store, and clears everything on disk
+        self.set(key, value)
+    }
+
+    /// Adds or updates the values corresponding to the keys of the pairs yielded by `pairs`,
+    /// flushing the log file and the index file only once for the whole batch.
+    /// It returns the number of pairs written.
+    ///
+    /// By default, the pairs are [set](Storage::set) one at a time
     ///
     /// # Errors
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case any key is empty. The pairs before it
+    ///   are still written
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
-    /// is not accessible
+    ///   is not accessible
     ///
     /// [io::Error]: std::io::Error
-    fn clear(&mut self) -> io::Result<()>;
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    fn set_many_from_iter(
+        &mut self,
+        pairs: &mut dyn Iterator<Item = (String, String)>,
+    ) -> io::Result<usize> {
+        let mut count = 0;
+        for (key, value) in pairs {
+            self.set(&key, &value)?;
+            count += 1;
+        }
 
-    /// Deletes all key-value pairs that have been previously marked for 'delete'
-    /// when store.Delete(key) was called on them.
+        Ok(count)
+    }
+
+    /// Writes any sets buffered in memory to the log file, rolling it if it is now too big
+    ///
+    /// By default, nothing is buffered so there is nothing to write
     ///
     /// # Errors
     /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
-    /// is not accessible
+    ///   is not accessible
     ///
     /// [io::Error]: std::io::Error
-    fn vacuum(&self) -> io::Result<()>;
-}
-
-/// `Store` is the actual internal store that saves data both in memory and on disk
-/// It implements the [Storage] trait
-pub(crate) struct Store {
-    db_path: PathBuf,
-    max_file_size_kb: f64,
-    cache: Cache,
-    memtable: HashMap<String, String>,
-    index: HashMap<String, String>,
-    data_files: Vec<String>,
-    current_log_file: String,
-    current_log_file_path: PathBuf,
-    del_file_path: PathBuf,
-    index_file_path: PathBuf,
-}
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 
-impl Storage for Store {
-    fn load(&mut self) -> io::Result<()> {
-        fs::create_dir_all(self.db_path.clone())?;
-        self.create_index_file_if_not_exists()?;
-        self.create_del_file_if_not_exists()?;
-        self.create_log_file_if_not_exists()?;
-        self.vacuum()?;
-        self.load_file_props_from_disk()?;
-        self.load_index_from_disk()?;
-        self.load_memtable_from_disk()
+    /// Rolls the current log file into a data file, whatever its size, and starts a fresh log file
+    /// so that the memtable is empty. It does nothing if the memtable is already empty
+    ///
+    /// By default, there is no log file so there is nothing to roll
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn checkpoint(&mut self) -> io::Result<()> {
+        Ok(())
     }
 
-    fn set(&mut self, key: &str, value: &str) -> Result<(), CorruptedDataError> {
-        let timestamped_key = self.get_timestamped_key(key).or_else(|_| {
-            self.remove_timestamped_key_for_key_if_exists(key)
-                .unwrap_or(());
-            Err(CorruptedDataError)
-        })?;
+    /// Retrieves the value corresponding to the given key
+    ///
+    /// # Errors
+    /// - [NotFoundError] in case the key is not found in the store
+    /// - Panics with [CorruptedDataError] in case the data on disk is not
+    /// consistent with that in memory
+    ///
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn get(&mut self, key: &str) -> Result<String, NotFoundError>;
 
-        self.save_key_value_pair(&timestamped_key, value)
-            .or_else(|_| {
-                self.delete_key_value_pair_if_exists(&timestamped_key)
-                    .unwrap_or(());
-                self.remove_timestamped_key_for_key_if_exists(key)
-                    .unwrap_or(());
-                Err(CorruptedDataError)
-            })
+    /// Retrieves the value corresponding to the given key, or `None` if the key is not found
+    ///
+    /// By default, it is [Storage::get] with any error turned into `None`
+    ///
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn try_get(&mut self, key: &str) -> io::Result<Option<String>> {
+        Ok(self.get(key).ok())
     }
 
-    fn get(&mut self, key: &str) -> Result<String, NotFoundError> {
-        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?;
-        let timestamped_key = timestamped_key.clone();
-        self.get_value_for_key(&timestamped_key)
-            .or_else(|err| panic!("{}", err))
+    /// Retrieves the time, in nanoseconds since the UNIX epoch, at which the value of the given key
+    /// was last set
+    ///
+    /// By default, the last-modified timestamps are not tracked
+    ///
+    /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [NotTrackedError] wrapped in an [io::Error] in case the key was last set without
+    ///   the last-modified timestamp being tracked
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [NotTrackedError]: crate::errors::NotTrackedError
+    fn last_modified(&mut self, key: &str) -> io::Result<u64> {
+        self.get(key)?;
+        Err(NotTrackedError.into())
     }
 
-    fn delete(&mut self, key: &str) -> Result<(), NotFoundError> {
-        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?;
-
-        utils::delete_key_values_from_file(&self.index_file_path, &vec![key.to_string()])
-            .unwrap_or_else(|_| panic!("{}", CorruptedDataError));
-
-        let new_file_entry = format!("{}{}", timestamped_key, TOKEN_SEPARATOR);
-
-        utils::append_to_file(&self.del_file_path, &new_file_entry)
-            .unwrap_or_else(|_| panic!("{}", CorruptedDataError));
-
-        self.index.remove(key);
-
-        Ok(())
+    /// Retrieves the values corresponding to the given keys as a map of key to value,
+    /// skipping any keys that are not found or whose values cannot be read. The values are fetched in the order in which their
+    /// keys were created so that each data file is loaded into the cache at most once
+    ///
+    /// By default, the keys are got with [Storage::get] in the order given
+    fn get_map(&mut self, keys: &[&str]) -> HashMap<String, String> {
+        keys.iter()
+            .filter_map(|key| self.get(key).ok().map(|value| (key.to_string(), value)))
+            .collect()
     }
 
-    fn clear(&mut self) -> io::Result<()> {
-        self.index.clear();
-        self.clear_disk()?;
-        self.load()
+    /// Retrieves the values corresponding to the given keys, returning a result for each key
+    /// in the same order as `keys`. The values are fetched in the order in which their
+    /// keys were created so that each data file is loaded into the cache at most once
+    ///
+    /// By default, the keys are got with [Storage::get] in the order given
+    ///
+    /// # Errors
+    /// Each result is one of:
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn multi_get(&mut self, keys: &[&str]) -> Vec<io::Result<String>> {
+        keys.iter()
+            .map(|key| self.get(key).map_err(io::Error::from))
+            .collect()
     }
 
-    fn vacuum(&self) -> io::Result<()> {
-        let file_exts_to_vacuum = vec![LOG_FILE_EXT, DATA_FILE_EXT];
-        let keys_to_delete = self.get_keys_to_delete()?;
-
-        if keys_to_delete.len() == 0 {
-            return Ok(());
-        }
-
-        let files_to_vacuum = utils::get_files_with_extensions(&self.db_path, file_exts_to_vacuum)?;
-
-        for filename in files_to_vacuum {
-            let path = self.db_path.join(filename);
-            utils::delete_key_values_from_file(&path, &keys_to_delete)?;
-        }
+    /// Loads the data file holding the timestamps from `start` up to `end` into the cache so that
+    /// subsequent reads of keys in that range are served from memory. Only the data file
+    /// holding `start` is loaded if the range spans many.
+    /// Nothing is loaded if the range is empty or lies within the current log file, which is
+    /// always in memory.
+    ///
+    /// By default, there is no cache so nothing is loaded
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn preload_range(&mut self, _start: &str, _end: &str) -> io::Result<()> {
+        Ok(())
+    }
 
-        // Clear del file
-        fs::write(&self.del_file_path, "")?;
+    /// Captures a read-only view of the store as it is at this moment.
+    /// See [Snapshot] for the isolation it offers. Storages other than the file-based store
+    /// can capture one with [Snapshot::from_pairs]
+    fn snapshot(&mut self) -> Snapshot;
 
-        Ok(())
+    /// Checks whether the most recent value read was served from memory
+    /// rather than requiring data to be loaded from disk
+    ///
+    /// By default, no read is reported as cached
+    fn last_read_was_cached(&self) -> bool {
+        false
     }
-}
 
-impl Store {
-    /// Creates a new instance of Store
+    /// Checks whether the store is in a read-only degraded mode because its index file or
+    /// del file could not be written e.g. the database folder became read-only. Writes are then rejected
+    /// with a [DegradedError] until the store is loaded again, while reads still work
     ///
-    /// `db_path` is the path to the folder to contain the database files.
+    /// By default, the storage is never degraded
     ///
-    /// `max_file_size_kb` is the maximum size in kilobytes that the data files can be. Beyond that,
+    /// [DegradedError]: crate::errors::DegradedError
+    fn is_degraded(&self) -> bool {
+        false
+    }
+
+    /// Reports whether the given key is present, deleted but not yet vacuumed, or absent
+    ///
+    /// By default, deleted keys are not kept so keys are only ever present or absent
     ///
     /// # Errors
-    /// - [io::Error] I/O errors e.g file permissions, missing files in case the `db_path` database folder
-    /// is not accessible
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
     /// [io::Error]: std::io::Error
-    pub(crate) fn new(db_path: &str, max_file_size_kb: f64) -> Store {
-        let db_path = PathBuf::from(Path::new(db_path));
-        let del_file_path = db_path.join(DEL_FILENAME);
-        let index_file_path = db_path.join(INDEX_FILENAME);
-
-        Store {
-            db_path,
-            max_file_size_kb,
-            cache: Cache::new_empty(),
-            memtable: Default::default(),
-            index: Default::default(),
-            data_files: vec![],
-            current_log_file: "".to_string(),
-            current_log_file_path: PathBuf::new(),
-            del_file_path,
-            index_file_path,
+    fn status(&mut self, key: &str) -> io::Result<KeyStatus> {
+        match self.get(key) {
+            Ok(_) => Ok(KeyStatus::Present),
+            Err(_) => Ok(KeyStatus::Absent),
         }
     }
 
-    /// Creates a new index file if there is no index file in the database folder
+    /// Removes the key-value pair corresponding to the passed key, returning the value removed
     ///
     /// # Errors
+    /// - [InvalidKeyError] wrapped in an [io::Error] in case the key is empty
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
-    /// See [utils::create_file_if_not_exist]
-    // #[inline]
-    fn create_index_file_if_not_exists(&self) -> io::Result<()> {
-        utils::create_file_if_not_exist(&self.index_file_path)
-    }
+    /// [io::Error]: std::io::Error
+    /// [InvalidKeyError]: crate::errors::InvalidKeyError
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn delete(&mut self, key: &str) -> io::Result<String>;
 
-    /// Creates a new del file if there is no del file in the database folder
+    /// Restores the key-value pair of a key that was deleted but not yet vacuumed,
+    /// by adding the key back to the index and removing it from the del file.
+    /// Its value is still in the log file or its data file since values are only removed on vacuum
+    ///
+    /// By default, deleted keys are not kept so none can be restored
     ///
     /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not [deleted] e.g. it was
+    ///   vacuumed
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
-    /// See [utils::create_file_if_not_exist]
-    // #[inline]
-    fn create_del_file_if_not_exists(&self) -> io::Result<()> {
-        utils::create_file_if_not_exist(&self.del_file_path)
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [deleted]: KeyStatus::Deleted
+    fn undelete(&mut self, _key: &str) -> io::Result<()> {
+        Err(NotFoundError.into())
     }
 
-    /// Creates a new log file if there is no .log file in the database folder
+    /// Renames the given `key` to `new_key`, keeping its value.
+    /// The value is saved afresh under `new_key` as though it were a new write, and the old
+    /// key-value pair is marked for deletion. Any value that `new_key` had is overwritten.
+    ///
+    /// By default, the value is [set](Storage::set) under `new_key` before `key` is [deleted](Storage::delete)
     ///
     /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - See [Storage::set] for the errors that can occur on saving the value under `new_key`
     ///
-    /// See [utils::create_file_if_not_exist] and [Store::create_new_log_file]
-    // #[inline]
-    fn create_log_file_if_not_exists(&mut self) -> io::Result<()> {
-        let extensions = vec![LOG_FILE_EXT];
-        let log_files = utils::get_files_with_extensions(&self.db_path, extensions)?;
-
-        if log_files.len() > 0 {
-            self.current_log_file_path = self.db_path.join(&log_files[0]);
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    fn rename(&mut self, key: &str, new_key: &str) -> io::Result<()> {
+        let value = self.get(key)?;
+        if key == new_key {
             return Ok(());
         }
 
-        self.create_new_log_file()
+        self.set(new_key, &value)?;
+        self.delete(key).and(Ok(()))
     }
 
-    /// loads the attributes that depend on the things in the folder
+    /// Saves the value of the given `key` afresh under a new timestamped key, without changing it,
+    /// so that it is served from the memtable. The old key-value pair is marked for deletion.
+    /// Keys already in the memtable are left as they are
+    ///
+    /// By default, the value is simply [set](Storage::set) again
     ///
     /// # Errors
+    /// - [NotFoundError] wrapped in an [io::Error] in case the key is not found in the store
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    /// - See [Storage::set] for the errors that can occur on saving the value afresh
     ///
-    /// See [crate::utils::get_file_names_in_folder]
-    fn load_file_props_from_disk(&mut self) -> io::Result<()> {
-        self.data_files.clear();
-
-        let files_in_folder = utils::get_file_names_in_folder(&self.db_path)?;
-
-        for filename in files_in_folder {
-            let parts: Vec<&str> = filename.rsplitn(2, ".").collect();
-            if parts.len() < 2 {
-                continue;
-            }
-
-            let ext: &str = parts[0];
-            let filename: &str = parts[1];
+    /// [io::Error]: std::io::Error
+    /// [NotFoundError]: crate::errors::NotFoundError
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn touch(&mut self, key: &str) -> io::Result<()> {
+        let value = self.get(key)?;
+        self.set(key, &value)
+    }
 
-            if ext == LOG_FILE_EXT {
-                self.current_log_file = filename.to_string()
-            } else if ext == DATA_FILE_EXT {
-                self.data_files.push(filename.to_string())
-            }
+    /// Removes all key-value pairs whose keys start with the given `prefix`,
+    /// returning the number of keys removed
+    ///
+    /// By default, the keys are found with [Storage::filter] and [deleted](Storage::delete) one at a time
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn clear_prefix(&mut self, prefix: &str) -> io::Result<usize> {
+        let pairs = self.filter(&|key, _| key.starts_with(prefix))?;
+        for (key, _) in &pairs {
+            self.delete(key)?;
         }
 
-        self.data_files.sort();
-
-        Ok(())
+        Ok(pairs.len())
     }
 
-    /// Loads the index from the index file
+    /// Retrieves all the key-value pairs for which `predicate(key, value)` is true,
+    /// in the order in which the keys were created
     ///
-    /// # Error
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
     ///
-    /// See [fs::read_to_string] and [utils::extract_key_values_from_str]
-    // #[inline]
-    fn load_index_from_disk(&mut self) -> io::Result<()> {
-        let content = fs::read_to_string(&self.index_file_path)?;
-        self.index = utils::extract_key_values_from_str(&content)?;
-        Ok(())
-    }
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn filter(
+        &mut self,
+        predicate: &dyn Fn(&str, &str) -> bool,
+    ) -> io::Result<Vec<(String, String)>>;
 
-    /// Loads the memtable from the log file
+    /// Retrieves all the key-value pairs whose keys match the glob `pattern`,
+    /// in the order in which the keys were created. In the pattern, `*` matches any sequence
+    /// of characters, `?` matches exactly one character and `\` escapes the next character.
+    /// Only the keys in the index are matched so values are read only for the matching keys
     ///
-    /// # Error
+    /// By default, the keys are matched with [Storage::filter]
     ///
-    /// See [fs::read_to_string] and [utils::extract_key_values_from_str]
-    // #[inline]
-    fn load_memtable_from_disk(&mut self) -> io::Result<()> {
-        let content = fs::read_to_string(&self.current_log_file_path)?;
-        self.memtable = utils::extract_key_values_from_str(&content)?;
-        Ok(())
+    /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
+    ///
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn scan_glob(&mut self, pattern: &str) -> io::Result<Vec<(String, String)>> {
+        self.filter(&|key, _| utils::matches_glob(pattern, key))
     }
 
-    /// Creates a new log file basing on the current timestamp
+    /// Checks whether any key in the store has the given `value`.
+    /// There is no index of values so all key-value pairs are scanned
+    ///
+    /// By default, the values are matched with [Storage::filter]
     ///
     /// # Errors
+    /// - [CorruptedDataError] wrapped in an [io::Error] in case the data on disk is inconsistent
+    ///   with that in memory
     ///
-    /// See [crate::utils::get_current_timestamp_str] and [utils::create_file_if_not_exist]
-    fn create_new_log_file(&mut self) -> io::Result<()> {
-        let log_file_name = utils::get_current_timestamp_str()?;
-        let log_file_path = self
-            .db_path
-            .join(format!("{}.{}", log_file_name, LOG_FILE_EXT));
-
-        utils::create_file_if_not_exist(&log_file_path)?;
-
-        // update struct's props
-        self.current_log_file = log_file_name;
-        self.current_log_file_path = log_file_path;
-        Ok(())
+    /// [io::Error]: std::io::Error
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    fn contains_value(&mut self, value: &str) -> io::Result<bool> {
+        Ok(!self.filter(&|_, v| v == value)?.is_empty())
     }
 
-    /// Reads the del file and gets the keys to be deleted
+    /// Cross-checks the index against the memtable, the data files and the del file,
+    /// reporting every inconsistency found. This is useful when data corruption is suspected.
     ///
-    /// # Errors
-    ///
-    /// See [fs::read_to_string]
-    // #[inline]
-    fn get_keys_to_delete(&self) -> io::Result<Vec<String>> {
-        let content = fs::read_to_string(&self.del_file_path)?;
-        Ok(utils::extract_tokens_from_str(&content))
-    }
-
-    /// Gets the timestamped key corresponding to the given key in the index
-    /// If there is none, it creates a new timestamped key and adds it to the index and the index file
+    /// By default, there is nothing to cross-check so no inconsistencies are reported
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
-    /// It will return a [CorruptedDataError] if it encounters any issues with creating timestamp
-    /// or adding it to the index file
-    ///
-    /// [CorruptedDataError]: crate::errors::CorruptedDataError
-    fn get_timestamped_key(&mut self, key: &str) -> io::Result<String> {
-        if let Some(k) = self.index.get(key) {
-            return Ok(k.to_string());
-        }
-
-        let timestamp = utils::get_current_timestamp_str()?;
-        let timestamped_key = format!("{}-{}", timestamp, key);
-        let new_file_entry = format!(
-            "{}{}{}{}",
-            key, KEY_VALUE_SEPARATOR, timestamped_key, TOKEN_SEPARATOR
-        );
+    /// [io::Error]: std::io::Error
+    fn verify(&self) -> io::Result<VerifyReport> {
+        Ok(VerifyReport::default())
+    }
 
-        self.index.insert(key.to_string(), timestamped_key.clone());
-        utils::append_to_file(&self.index_file_path, &new_file_entry)?;
+    /// Releases the memory held by the index, the memtable and the cache beyond what their
+    /// current contents need e.g. after a large batch of keys has been deleted
+    ///
+    /// By default, nothing is released
+    fn shrink_memory(&mut self) {}
 
-        Ok(timestamped_key)
+    /// Counts how many live keys are served from the memtable and how many from the data files
+    ///
+    /// By default, there is no memtable or data files so both counts are zero
+    fn keys_count_by_location(&self) -> KeyLocationCounts {
+        KeyLocationCounts::default()
     }
 
-    /// Removes the key and timestamped key from the index
-    /// and the index file if it exists
+    /// Counts the distinct keys listed in the del file i.e. those awaiting the next vacuum
+    ///
+    /// By default, deletes are not deferred so none are pending
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
     ///
-    /// See [utils::delete_key_values_from_file]
-    // #[inline]
-    fn remove_timestamped_key_for_key_if_exists(&mut self, key: &str) -> io::Result<()> {
-        if let Some(_) = self.index.get(key) {
-            self.index.remove(key);
-            utils::delete_key_values_from_file(&self.index_file_path, &vec![key.to_string()])?;
-        }
-
-        Ok(())
+    /// [io::Error]: std::io::Error
+    fn pending_deletes(&self) -> io::Result<usize> {
+        Ok(0)
     }
 
-    /// Saves the key value pair in memtable and log file if it is newer than log file
-    /// or in cache and in the corresponding dataFile if the key is old
+    /// Sums up the sizes in bytes of the index file, the del file, the log files and
+    /// the data files. Sets still buffered in memory are not counted
     ///
-    /// # Error
+    /// By default, there are no files so the size is zero
     ///
-    /// See [Store::save_key_value_pair_to_memtable], [Store::load_cache_containing_key],
-    /// [Store::save_key_value_pair_to_cache]
-    // #[inline]
-    fn save_key_value_pair(&mut self, timestamped_key: &str, value: &str) -> io::Result<()> {
-        if timestamped_key.to_string() >= self.current_log_file {
-            return self.save_key_value_pair_to_memtable(timestamped_key, value);
-        }
-
-        if !self.cache.is_in_range(timestamped_key) {
-            self.load_cache_containing_key(timestamped_key)?;
-        }
-
-        self.save_key_value_pair_to_cache(timestamped_key, value)
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn size_on_disk(&self) -> io::Result<u64> {
+        Ok(0)
     }
 
-    /// Deletes the given key and its value from
-    /// the index, the cache or the memtable, the log file or any data file
+    /// Computes, for every data file and log file, the ratio of the bytes taken up by
+    /// key-value pairs marked for deletion to the size of the file, sorted by file name.
+    /// Files with the highest ratios gain the most from being vacuumed
+    ///
+    /// By default, there are no files so none are listed
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible, or of kind [io::ErrorKind::InvalidData] in case a file is malformed
     ///
-    /// See [Store::persist_cache_to_disk] and [utils::persist_map_data_to_file]
-    // #[inline]
-    fn delete_key_value_pair_if_exists(&mut self, key: &str) -> io::Result<()> {
-        if self.cache.is_in_range(key) {
-            self.cache.remove(key);
-            return self.persist_cache_to_disk();
-        }
+    /// [io::Error]: std::io::Error
+    fn fragmentation(&self) -> io::Result<Vec<(String, f64)>> {
+        Ok(vec![])
+    }
 
-        if key.to_string() >= self.current_log_file {
-            self.memtable.remove(key);
-            return utils::persist_map_data_to_file(&self.memtable, &self.current_log_file_path);
-        }
+    /// Gathers the metrics of the store in one go. See [Stats]
+    ///
+    /// By default, all the metrics are zero
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn stats(&self) -> io::Result<Stats> {
+        Ok(Stats::default())
+    }
 
-        Ok(())
+    /// Returns the key-value pairs in the memtable i.e. those not yet rolled into data files,
+    /// in the order in which they were created
+    ///
+    /// By default, there is no memtable so none are returned
+    fn recent_entries(&self) -> Vec<(String, String)> {
+        vec![]
     }
 
-    /// Saves the key value pair to memtable and persists memtable
-    /// to current log file
+    /// Reads the data file `filename` e.g. "01655375120328185000.cky", returning its
+    /// timestamped keys and values as saved on disk, sorted by timestamped key.
+    /// The cache is left untouched
+    ///
+    /// By default, there are no data files so an [io::Error] of kind [io::ErrorKind::NotFound] is returned
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the data file
+    ///   is not accessible, or of kind [io::ErrorKind::InvalidData] in case it is malformed
     ///
-    /// See [crate::utils::persist_map_data_to_file] and [Store::roll_log_file_if_too_big]
-    // #[inline]
-    fn save_key_value_pair_to_memtable(
-        &mut self,
-        timestamped_key: &str,
-        value: &str,
-    ) -> io::Result<()> {
-        self.memtable
-            .insert(timestamped_key.to_string(), value.to_string());
-        utils::persist_map_data_to_file(&self.memtable, &self.current_log_file_path)?;
-        self.roll_log_file_if_too_big()
+    /// [io::Error]: std::io::Error
+    fn iter_data_file(&self, _filename: &str) -> io::Result<Vec<(String, String)>> {
+        Err(io::ErrorKind::NotFound.into())
     }
 
-    /// Saves the key value pair to cache and persists cache
-    /// to corresponding data file
+    /// Sets all the key-value pairs of the ckydb database at `other_db_path` into this store,
+    /// returning the number of pairs written. For keys found in both databases, the `policy`
+    /// decides which value is kept. The other database is left unchanged
+    ///
+    /// By default, merging is not supported so an [io::Error] of kind [io::ErrorKind::Unsupported] is returned
     ///
     /// # Errors
+    /// - [NotADatabaseError] wrapped in an [io::Error] if `other_db_path` is not
+    ///   an existing ckydb database
+    /// - See [Storage::set_many_from_iter] for the errors that can occur on saving the pairs
     ///
-    /// See [Store::persist_cache_to_disk]
-    // #[inline]
-    fn save_key_value_pair_to_cache(
-        &mut self,
-        timestamped_key: &str,
-        value: &str,
-    ) -> io::Result<()> {
-        self.cache.update(timestamped_key, value);
-        self.persist_cache_to_disk()
+    /// [io::Error]: std::io::Error
+    /// [NotADatabaseError]: crate::errors::NotADatabaseError
+    fn merge_from(&mut self, _other_db_path: &str, _policy: MergePolicy) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
     }
 
-    /// Loads the cache with data containing the timestampedKey
+    /// Resets the entire Store, and clears everything on disk
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    /// is not accessible
     ///
-    /// A [crate::errors::CorruptedDataError] will be returned if the key does not fall in
-    /// an of the ranges of timestamps represented by the data file names and the log file name.
-    /// Other errors may occur as seen in
-    /// [std::fs::read_to_string] and [utils::extract_key_values_from_str]
-    // #[inline]
-    fn load_cache_containing_key(&mut self, key: &str) -> io::Result<()> {
-        let (start, end) = self.get_timestamp_range_for_key(key).ok_or(io::Error::new(
-            io::ErrorKind::InvalidData,
-            CorruptedDataError,
-        ))?;
-        // get data from disk
-        let file_path = self.db_path.join(format!("{}.{}", start, DATA_FILE_EXT));
-        let content_str = fs::read_to_string(&file_path)?;
-        let map_data = utils::extract_key_values_from_str(&content_str)?;
-
-        self.cache = Cache::new(map_data, &start, &end);
-        Ok(())
+    /// [io::Error]: std::io::Error
+    fn clear(&mut self) -> io::Result<()>;
+
+    /// Resets the entire Store immediately, leaving the deletion of everything on disk
+    /// to a background task whose handle is returned
+    ///
+    /// By default, the storage is [cleared](Storage::clear) right away and the task returned does nothing
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    ///   is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    fn clear_async(&mut self) -> io::Result<JoinHandle<io::Result<()>>> {
+        self.clear()?;
+        Ok(thread::spawn(|| Ok(())))
     }
 
-    /// Rolls the current log file if it has exceeded the maximum size it should have
+    /// Deletes all key-value pairs that have been previously marked for 'delete'
+    /// when store.Delete(key) was called on them.
+    /// It also coalesces adjacent data files that are too small, if so configured.
     ///
     /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the database folder
+    /// is not accessible
     ///
-    /// See [crate::utils::get_file_size], [std::fs::rename] and [Store::create_new_log_file]
-    fn roll_log_file_if_too_big(&mut self) -> io::Result<()> {
-        let log_file_size = utils::get_file_size(&self.current_log_file_path)?;
+    /// [io::Error]: std::io::Error
+    fn vacuum(&mut self) -> io::Result<()>;
+}
+
+/// `KeyStatus` is the status of a key in the database as reported by [Storage::status]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// The key has a value in the database
+    Present,
+    /// The key was deleted but its value has not yet been vacuumed
+    Deleted,
+    /// The key was never set, or it was deleted and its value has already been vacuumed
+    Absent,
+}
+
+/// `MergePolicy` decides whose value is kept when a key being [merged] from another database
+/// already exists in this database
+///
+/// [merged]: Storage::merge_from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the value in this database
+    KeepMine,
+    /// Overwrite the value in this database with that in the other database
+    TakeTheirs,
+}
+
+/// `VerifyReport` is the result of [verifying] the consistency of the database
+///
+/// [verifying]: Storage::verify
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VerifyReport {
+    /// All the inconsistencies found, in the order of the timestamped keys they concern
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+impl VerifyReport {
+    /// Checks whether no inconsistencies were found
+    pub fn is_ok(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+/// `KeyLocationCounts` is the number of live keys served from each [location] on reads
+///
+/// [location]: Storage::keys_count_by_location
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyLocationCounts {
+    /// The number of keys whose values are in the memtable i.e. the current log file
+    pub memtable: usize,
+    /// The number of keys whose values are in the data files, read through the cache
+    pub data_files: usize,
+}
+
+/// `Stats` is a set of metrics of the database at the moment they were [gathered]
+///
+/// [gathered]: Storage::stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// The number of live keys
+    pub keys: usize,
+    /// The number of data files
+    pub data_files: usize,
+    /// The total size in bytes of all the database's files on disk
+    pub size_on_disk_bytes: u64,
+    /// The number of deleted keys yet to be vacuumed
+    pub pending_deletes: usize,
+    /// The number of values read from memory i.e. the memtable or the cache, since connecting
+    pub cache_hits: u64,
+    /// The number of values read after loading their data files from disk, since connecting
+    pub cache_misses: u64,
+}
+
+impl Stats {
+    /// Formats the stats as a JSON object e.g. for logging or exposing them for monitoring
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"keys\":{},\"data_files\":{},\"size_on_disk_bytes\":{},\"pending_deletes\":{},\"cache_hits\":{},\"cache_misses\":{}}}",
+            self.keys,
+            self.data_files,
+            self.size_on_disk_bytes,
+            self.pending_deletes,
+            self.cache_hits,
+            self.cache_misses
+        )
+    }
+}
+
+/// `Inconsistency` is a single inconsistency found when [verifying] the database
+///
+/// [verifying]: Storage::verify
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// The timestamped key of `key` in the index does not fall in the range of any data file
+    NoFileForKey {
+        key: String,
+        timestamped_key: String,
+    },
+    /// The value of `key` is not in `file_name`, the file that should hold it
+    MissingValue {
+        key: String,
+        timestamped_key: String,
+        file_name: String,
+    },
+    /// The timestamped key of `key` in the index is listed in the del file
+    DeletedKeyInIndex {
+        key: String,
+        timestamped_key: String,
+    },
+    /// The data file `file_name` could not be read or is malformed
+    UnreadableFile { file_name: String },
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::NoFileForKey {
+                key,
+                timestamped_key,
+            } => write!(f, "no file holds key {} ({})", key, timestamped_key),
+            Inconsistency::MissingValue {
+                key,
+                timestamped_key,
+                file_name,
+            } => write!(
+                f,
+                "value of key {} ({}) is missing from {}",
+                key, timestamped_key, file_name
+            ),
+            Inconsistency::DeletedKeyInIndex {
+                key,
+                timestamped_key,
+            } => write!(
+                f,
+                "key {} ({}) is in the index yet marked for deletion",
+                key, timestamped_key
+            ),
+            Inconsistency::UnreadableFile { file_name } => {
+                write!(f, "file {} is unreadable", file_name)
+            }
+        }
+    }
+}
+
+/// `Store` is the actual internal store that saves data both in memory and on disk
+/// It implements the [Storage] trait
+///
+/// All its files, including the index file, are only ever changed through `&mut self`,
+/// so a store shared between threads e.g. by [crate::controller::Ckydb] and its vacuum task
+/// must be behind a lock, which then also serializes all appends to and rewrites of the index file
+pub(crate) struct Store {
+    db_path: PathBuf,
+    max_file_size_kb: f64,
+    folder: Folder,
+    max_log_entries: Option<usize>,
+    vacuum_on_load: bool,
+    fsync: FsyncPolicy,
+    max_value_size_bytes: Option<usize>,
+    split_data_files: bool,
+    resilient_reads: bool,
+    cache_memory_budget_kb: Option<f64>,
+    min_data_file_kb: Option<f64>,
+    flush_every_n: Option<usize>,
+    track_last_modified: bool,
+    retry_policy: Option<RetryPolicy>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<[u8; 32]>,
+    roll_hook: Option<RollHook>,
+    load_progress_hook: Option<LoadProgressHook>,
+    unflushed_sets_count: usize,
+    last_read_was_cached: bool,
+    cache_hits: u64,
+    cache_misses: u64,
+    is_degraded: bool,
+    clock: Box<dyn Clock + Send>,
+    last_timestamp: u128,
+    cache: Cache,
+    // the ranges cached before the current one, least recently used first
+    cached_ranges: VecDeque<Cache>,
+    // the data file copies of the snapshots taken, pruned of dropped snapshots on every snapshot
+    snapshots: Vec<Weak<DataFileCopies>>,
+    memtable: HashMap<String, String>,
+    index: HashMap<String, String>,
+    keys_marked_for_deletion: HashSet<String>,
+    data_files: Vec<String>,
+    current_log_file: String,
+    current_log_file_path: PathBuf,
+    del_file_path: PathBuf,
+    index_file_path: PathBuf,
+}
 
-        if log_file_size >= self.max_file_size_kb {
-            let new_data_filename = format!("{}.{}", self.current_log_file, DATA_FILE_EXT);
-            fs::rename(
-                &self.current_log_file_path,
-                self.db_path.join(&new_data_filename),
-            )?;
+impl Storage for Store {
+    fn load(&mut self) -> io::Result<()> {
+        self.is_degraded = false;
+        self.folder.create_dir_all(&self.db_path)?;
+        self.pad_legacy_file_names()?;
+        self.create_index_file_if_not_exists()?;
+        self.create_del_file_if_not_exists()?;
+        self.create_log_file_if_not_exists()?;
 
-            self.memtable.clear();
-            self.data_files.push(self.current_log_file.clone());
-            // endure the data files are sorted
-            self.data_files.sort();
-            self.create_new_log_file()?;
+        if self.vacuum_on_load {
+            self.vacuum()?;
         }
 
+        let total_files = self.load_file_props_from_disk()?;
+        self.clear_cache();
+        self.load_index_from_disk()?;
+        self.report_load_progress(total_files, total_files);
+        self.load_memtable_from_disk()?;
+        #[cfg(feature = "encryption")]
+        self.verify_encryption_key()?;
         Ok(())
     }
 
-    /// Persists the current cache to its corresponding data file
-    ///
-    /// # Errors
-    ///
-    /// See [crate::utils::persist_map_data_to_file]
-    // #[inline]
-    fn persist_cache_to_disk(&self) -> io::Result<()> {
-        let data_file_path = self
+    fn reload(&mut self) -> io::Result<()> {
+        // buffered sets would otherwise be lost when the memtable is reloaded from disk
+        self.flush()?;
+        let total_files = self.load_file_props_from_disk()?;
+        self.current_log_file_path = self
             .db_path
-            .join(format!("{}.{}", self.cache.start, DATA_FILE_EXT));
-        utils::persist_map_data_to_file(&self.cache.data, &data_file_path)
+            .join(format!("{}.{}", self.current_log_file, LOG_FILE_EXT));
+        self.clear_cache();
+        self.load_index_from_disk()?;
+        self.report_load_progress(total_files, total_files);
+        self.load_memtable_from_disk()?;
+        #[cfg(feature = "encryption")]
+        self.verify_encryption_key()?;
+        Ok(())
     }
 
-    /// Returns the range of timestamps between which
-    /// the key lies. The timestamps are got from the names of the data files and the current log file
-    /// It will return None if there is no relevant timestamp range from the available data file names
-    /// and log file names
-    // #[inline]
-    fn get_timestamp_range_for_key(&self, key: &str) -> Option<(String, String)> {
-        let mut timestamps = self.data_files.clone();
-        timestamps.push(self.current_log_file.clone());
-        let key_as_string = key.to_string();
-
-        for i in 1..timestamps.len() {
-            let current = &timestamps[i];
-            if *current > key_as_string {
-                return Some((timestamps[i - 1].clone(), current.clone()));
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.ensure_not_degraded()?;
+        self.validate_key_value_pair(key, value)?;
+        let value = &self.encode_value(value)?;
+
+        let timestamped_key = self.get_timestamped_key(key).or_else(|err| {
+            if self.is_degraded {
+                return Err(err);
             }
-        }
 
-        None
+            self.remove_timestamped_key_for_key_if_exists(key)
+                .unwrap_or(());
+            Err(io::Error::from(CorruptedDataError))
+        })?;
+
+        self.save_key_value_pair(&timestamped_key, value)
+            .or_else(|err| {
+                // the key-value pair was saved if only the roll hook failed
+                if is_roll_hook_error(&err) {
+                    return Err(err);
+                }
+
+                self.delete_key_value_pair_if_exists(&timestamped_key)
+                    .unwrap_or(());
+                self.remove_timestamped_key_for_key_if_exists(key)
+                    .unwrap_or(());
+                Err(io::Error::from(CorruptedDataError))
+            })
     }
 
-    /// Gets the value corresponding to a given timestampedKey
-    ///
-    /// # Errors
-    ///
-    /// It will return [crate::errors::CorruptedDataError] if the data on disk is inconsistent
-    /// with what is expected in memory e.g. if unable to load cache from disk, or cache or memtable
-    /// don't contain the key yet they should contain it.
-    ///
-    /// Obviously [crate::errors::CorruptedDataError] has a very minute chance of happening
-    // #[inline]
-    fn get_value_for_key(&mut self, timestamped_key: &str) -> Result<String, CorruptedDataError> {
-        if timestamped_key.to_string() >= self.current_log_file {
-            let value = self
-                .memtable
-                .get(timestamped_key)
-                .ok_or(CorruptedDataError)?;
-            return Ok(value.to_string());
+    fn insert_new(&mut self, key: &str, value: &str) -> io::Result<()> {
+        if self.index.contains_key(key) {
+            return Err(io::Error::from(AlreadyExistsError {
+                key: key.to_string(),
+            }));
         }
 
-        if !self.cache.is_in_range(timestamped_key) {
-            self.load_cache_containing_key(timestamped_key)
-                .or(Err(CorruptedDataError))?;
+        self.set(key, value)
+    }
+
+    fn set_many_from_iter(
+        &mut self,
+        pairs: &mut dyn Iterator<Item = (String, String)>,
+    ) -> io::Result<usize> {
+        self.ensure_not_degraded()?;
+        let mut new_index_entries: Vec<(String, String)> = vec![];
+        let mut count = 0;
+        let mut result = Ok(());
+
+        for (key, value) in pairs {
+            result = self.stage_key_value_pair(&key, &value, &mut new_index_entries);
+            if result.is_err() {
+                break;
+            }
+            count += 1;
         }
 
-        let value = self.cache.get(timestamped_key).ok_or(CorruptedDataError)?;
-        Ok(value.to_string())
+        let new_file_entries = utils::convert_key_values_to_str(new_index_entries.iter().cloned());
+        if utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder
+                .append_to_file(&self.index_file_path, &new_file_entries)
+        })
+        .is_err()
+        {
+            // the index file can no longer be relied upon so writes are stopped altogether
+            for (key, timestamped_key) in new_index_entries {
+                self.index.remove(&key);
+                self.memtable.remove(&timestamped_key);
+            }
+            self.is_degraded = true;
+            return Err(DegradedError.into());
+        }
+        // any buffered sets are persisted along with the pairs
+        self.unflushed_sets_count = 0;
+        self.persist_memtable_to_disk()?;
+        result.and(Ok(count))
     }
 
-    /// Deletes all files in the database folder
-    ///
-    /// # Errors
-    ///
-    /// See [fs::remove_dir_all]
-    // #[inline]
-    fn clear_disk(&self) -> io::Result<()> {
-        fs::remove_dir_all(&self.db_path)
+    fn flush(&mut self) -> io::Result<()> {
+        if self.unflushed_sets_count == 0 {
+            return Ok(());
+        }
+
+        self.unflushed_sets_count = 0;
+        self.persist_memtable_to_disk()
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::cache::{Cache, Caching};
-    use crate::constants::{DEL_FILENAME, INDEX_FILENAME, KEY_VALUE_SEPARATOR, TOKEN_SEPARATOR};
-    use crate::store::{Storage, Store};
-    use crate::utils;
-    use serial_test::serial;
-    use std::collections::HashMap;
-    use std::ffi::OsString;
-    use std::fs;
-    use std::path::Path;
+    fn checkpoint(&mut self) -> io::Result<()> {
+        self.flush()?;
 
-    const DB_PATH: &str = "test_store_db";
-    const MAX_FILE_SIZE_KB: f64 = 320.0 / 1024.0;
-    const LOG_FILENAME: &str = "1655375171402014000.log";
-    const DATA_FILES: [&str; 2] = ["1655375120328185000.cky", "1655375120328186000.cky"];
-    const EMPTY_LIST: Vec<String> = vec![];
+        if self.memtable.is_empty() {
+            return Ok(());
+        }
 
-    #[test]
-    #[serial]
+        self.roll_log_file()
+    }
+
+    fn get(&mut self, key: &str) -> Result<String, NotFoundError> {
+        if key.is_empty() {
+            return Err(NotFoundError);
+        }
+
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?;
+        let timestamped_key = timestamped_key.clone();
+        self.get_value_for_key(&timestamped_key)
+            .or_else(|err| panic!("{}", err))
+    }
+
+    fn try_get(&mut self, key: &str) -> io::Result<Option<String>> {
+        let timestamped_key = match self.index.get(key) {
+            Some(timestamped_key) => timestamped_key.clone(),
+            None => return Ok(None),
+        };
+
+        let value = self.get_value_for_key(&timestamped_key)?;
+        Ok(Some(value))
+    }
+
+    fn last_modified(&mut self, key: &str) -> io::Result<u64> {
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?.clone();
+        let value = self.get_stored_value_for_key(&timestamped_key)?;
+
+        match utils::decode_value_with_modified_timestamp(&value) {
+            (Some(timestamp), _) if self.track_last_modified => {
+                Ok(u64::try_from(timestamp).or(Err(CorruptedDataError))?)
+            }
+            _ => Err(NotTrackedError.into()),
+        }
+    }
+
+    fn get_map(&mut self, keys: &[&str]) -> HashMap<String, String> {
+        let mut entries: Vec<(String, &str)> = keys
+            .iter()
+            .filter_map(|key| {
+                self.index
+                    .get(*key)
+                    .map(|timestamped_key| (timestamped_key.clone(), *key))
+            })
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        entries
+            .into_iter()
+            .filter_map(|(timestamped_key, key)| {
+                let value = self.get_value_for_key(&timestamped_key).ok()?;
+                Some((key.to_string(), value))
+            })
+            .collect()
+    }
+
+    fn multi_get(&mut self, keys: &[&str]) -> Vec<io::Result<String>> {
+        let mut results: Vec<io::Result<String>> =
+            keys.iter().map(|_| Err(NotFoundError.into())).collect();
+        let mut entries: Vec<(String, usize)> = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                self.index
+                    .get(*key)
+                    .map(|timestamped_key| (timestamped_key.clone(), i))
+            })
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        for (timestamped_key, i) in entries {
+            results[i] = self
+                .get_value_for_key(&timestamped_key)
+                .map_err(io::Error::from);
+        }
+
+        results
+    }
+
+    fn snapshot(&mut self) -> Snapshot {
+        let snapshot = Snapshot::new(
+            self.db_path.clone(),
+            self.folder.clone(),
+            self.index.clone(),
+            self.memtable.clone(),
+            self.data_files.clone(),
+            self.current_log_file.clone(),
+            self.track_last_modified,
+        );
+        #[cfg(feature = "encryption")]
+        let snapshot = snapshot.with_encryption_key(self.encryption_key);
+
+        self.snapshots
+            .retain(|data_file_copies| data_file_copies.strong_count() > 0);
+        self.snapshots.push(snapshot.get_data_file_copies());
+        snapshot
+    }
+
+    fn last_read_was_cached(&self) -> bool {
+        self.last_read_was_cached
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.is_degraded
+    }
+
+    fn preload_range(&mut self, start: &str, end: &str) -> io::Result<()> {
+        let is_empty_range = utils::compare_timestamps(end, start).is_lt();
+        let is_in_log_file = utils::compare_timestamps(start, &self.current_log_file).is_ge();
+
+        if is_empty_range || is_in_log_file || self.data_files.is_empty() {
+            return Ok(());
+        }
+
+        if !self.cache.is_in_range(start) {
+            self.load_cache_containing_key(start)?;
+            self.evict_cache_if_over_budget();
+        }
+
+        Ok(())
+    }
+
+    fn status(&mut self, key: &str) -> io::Result<KeyStatus> {
+        if self.index.contains_key(key) {
+            return Ok(KeyStatus::Present);
+        }
+
+        let is_deleted = self
+            .get_keys_to_delete()?
+            .iter()
+            .any(|timestamped_key| timestamped_key.split_once('-').map(|(_, k)| k) == Some(key));
+
+        if is_deleted {
+            Ok(KeyStatus::Deleted)
+        } else {
+            Ok(KeyStatus::Absent)
+        }
+    }
+
+    fn delete(&mut self, key: &str) -> io::Result<String> {
+        self.ensure_not_degraded()?;
+        if key.is_empty() {
+            return Err(InvalidKeyError.into());
+        }
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?.clone();
+        let value = self.get_value_for_key(&timestamped_key)?;
+
+        self.mark_keys_for_deletion(&vec![key.to_string()])?;
+
+        Ok(value)
+    }
+
+    fn undelete(&mut self, key: &str) -> io::Result<()> {
+        self.ensure_not_degraded()?;
+        if self.index.contains_key(key) {
+            return Err(NotFoundError.into());
+        }
+
+        let keys_to_delete = self.get_keys_to_delete()?;
+        // the latest deletion of the key is the one restored
+        let timestamped_key = keys_to_delete
+            .iter()
+            .rev()
+            .find(|timestamped_key| timestamped_key.split_once('-').map(|(_, k)| k) == Some(key))
+            .ok_or(NotFoundError)?
+            .clone();
+
+        let value = self
+            .get_value_for_deleted_key(&timestamped_key)?
+            .ok_or(NotFoundError)?;
+
+        // the key is added back to the index file first so that a failure leaves it deleted
+        let new_index_entry = utils::convert_key_values_to_str([(key, &timestamped_key)]);
+        utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder
+                .append_to_file(&self.index_file_path, &new_index_entry)
+        })?;
+        self.index.insert(key.to_string(), timestamped_key.clone());
+
+        let remaining_keys_to_delete = keys_to_delete.iter().filter(|k| **k != timestamped_key);
+        let del_file_content = utils::convert_tokens_to_str(remaining_keys_to_delete);
+        if let Err(err) = utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder.write(&self.del_file_path, &del_file_content)
+        }) {
+            self.remove_timestamped_key_for_key_if_exists(key)
+                .unwrap_or(());
+            return Err(err);
+        }
+
+        // the value is still in its file so it is only put back in memory
+        self.keys_marked_for_deletion.remove(&timestamped_key);
+        if self.is_in_log_file(&timestamped_key) {
+            self.memtable.insert(timestamped_key, value);
+        } else if self.cache.is_in_range(&timestamped_key) {
+            self.cache.update(&timestamped_key, &value);
+        } else {
+            self.cached_ranges
+                .retain(|cache| !cache.is_in_range(&timestamped_key));
+        }
+
+        Ok(())
+    }
+
+    fn rename(&mut self, key: &str, new_key: &str) -> io::Result<()> {
+        self.ensure_not_degraded()?;
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?.clone();
+        let value = self.get_value_for_key(&timestamped_key)?;
+
+        if key == new_key {
+            return Ok(());
+        }
+
+        self.validate_key_value_pair(new_key, &value)?;
+        let value = &self.encode_value(&value)?;
+
+        // the new key gets a fresh timestamped key, to be saved in the memtable.
+        // The value is saved before any old value of the new key is marked for deletion
+        // so that the old value is kept if saving fails
+        let new_timestamped_key = format!("{}-{}", self.get_new_timestamp_str()?, new_key);
+        let saved = self.save_key_value_pair(&new_timestamped_key, value);
+        // the key-value pair was saved if only the roll hook failed
+        if saved.as_ref().is_err_and(|err| !is_roll_hook_error(err)) {
+            self.delete_key_value_pair_if_exists(&new_timestamped_key)
+                .unwrap_or(());
+            return saved;
+        }
+
+        let replaced_timestamped_key = self.index.get(new_key).cloned();
+        if replaced_timestamped_key.is_some() {
+            self.mark_keys_for_deletion(&vec![new_key.to_string()])?;
+        }
+
+        if let Err(err) = self.add_timestamped_key_to_index(new_key, &new_timestamped_key) {
+            // the old value of the new key can still be read in degraded mode
+            if let Some(replaced_timestamped_key) = replaced_timestamped_key {
+                self.keys_marked_for_deletion
+                    .remove(&replaced_timestamped_key);
+                self.index
+                    .insert(new_key.to_string(), replaced_timestamped_key);
+            }
+            return Err(err);
+        }
+
+        self.mark_keys_for_deletion(&vec![key.to_string()])?;
+        saved
+    }
+
+    fn touch(&mut self, key: &str) -> io::Result<()> {
+        self.ensure_not_degraded()?;
+        let timestamped_key = self.index.get(key).ok_or(NotFoundError)?.clone();
+
+        if self.is_in_log_file(&timestamped_key) {
+            return Ok(());
+        }
+
+        // the value is kept as saved, including any last-modified timestamp.
+        // It is saved under the new timestamped key before the old one is marked for deletion
+        // so that the key is kept if saving fails
+        let value = self.get_stored_value_for_key(&timestamped_key)?;
+        let new_timestamped_key = format!("{}-{}", self.get_new_timestamp_str()?, key);
+        let saved = self.save_key_value_pair(&new_timestamped_key, &value);
+        // the key-value pair was saved if only the roll hook failed
+        if saved.as_ref().is_err_and(|err| !is_roll_hook_error(err)) {
+            self.delete_key_value_pair_if_exists(&new_timestamped_key)
+                .unwrap_or(());
+            return saved;
+        }
+
+        if let Err(err) = self.mark_keys_for_deletion(&vec![key.to_string()]) {
+            self.delete_key_value_pair_if_exists(&new_timestamped_key)
+                .unwrap_or(());
+            return Err(err);
+        }
+
+        if let Err(err) = self.add_timestamped_key_to_index(key, &new_timestamped_key) {
+            // the old value can still be read in degraded mode
+            self.keys_marked_for_deletion.remove(&timestamped_key);
+            self.index.insert(key.to_string(), timestamped_key);
+            return Err(err);
+        }
+
+        saved
+    }
+
+    fn clear_prefix(&mut self, prefix: &str) -> io::Result<usize> {
+        self.ensure_not_degraded()?;
+        let keys: Vec<String> = self
+            .index
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect();
+
+        if !keys.is_empty() {
+            self.mark_keys_for_deletion(&keys)?;
+        }
+
+        Ok(keys.len())
+    }
+
+    fn filter(
+        &mut self,
+        predicate: &dyn Fn(&str, &str) -> bool,
+    ) -> io::Result<Vec<(String, String)>> {
+        let mut results: Vec<(String, String)> = vec![];
+
+        self.for_each_entry(|key, value| {
+            if predicate(key, value) {
+                results.push((key.to_string(), value.to_string()));
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        Ok(results)
+    }
+
+    fn scan_glob(&mut self, pattern: &str) -> io::Result<Vec<(String, String)>> {
+        let mut entries: Vec<(String, String)> = self
+            .index
+            .iter()
+            .filter(|(key, _)| utils::matches_glob(pattern, key))
+            .map(|(key, timestamped_key)| (timestamped_key.clone(), key.clone()))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        let mut results: Vec<(String, String)> = Vec::with_capacity(entries.len());
+        for (timestamped_key, key) in entries {
+            let value = self.get_value_for_key(&timestamped_key)?;
+            results.push((key, value));
+        }
+
+        Ok(results)
+    }
+
+    fn contains_value(&mut self, value: &str) -> io::Result<bool> {
+        let mut is_found = false;
+
+        self.for_each_entry(|_, v| {
+            if v == value {
+                is_found = true;
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(())
+        })?;
+
+        Ok(is_found)
+    }
+
+    fn verify(&self) -> io::Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let keys_to_delete: HashSet<String> = self.get_keys_to_delete()?.into_iter().collect();
+        let mut data_files: HashMap<String, Option<HashMap<String, String>>> = Default::default();
+        let mut entries: Vec<(&String, &String)> = self
+            .index
+            .iter()
+            .map(|(key, timestamped_key)| (timestamped_key, key))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        for (timestamped_key, key) in entries {
+            if keys_to_delete.contains(timestamped_key) {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::DeletedKeyInIndex {
+                        key: key.clone(),
+                        timestamped_key: timestamped_key.clone(),
+                    });
+            }
+
+            let (file_name, data) = if self.is_in_log_file(timestamped_key) {
+                let file_name = format!("{}.{}", self.current_log_file, LOG_FILE_EXT);
+                (file_name, Some(&self.memtable))
+            } else if let Some((start, _)) = self.get_timestamp_range_for_key(timestamped_key) {
+                let file_name = format!("{}.{}", start, DATA_FILE_EXT);
+                if !data_files.contains_key(&file_name) {
+                    let data = self
+                        .folder
+                        .read_to_string(self.db_path.join(&file_name))
+                        .ok()
+                        .and_then(|content| utils::extract_key_values_from_str(&content).ok());
+                    if data.is_none() {
+                        report.inconsistencies.push(Inconsistency::UnreadableFile {
+                            file_name: file_name.clone(),
+                        });
+                    }
+                    data_files.insert(file_name.clone(), data);
+                }
+                let data = data_files.get(&file_name).and_then(Option::as_ref);
+                (file_name, data)
+            } else {
+                report.inconsistencies.push(Inconsistency::NoFileForKey {
+                    key: key.clone(),
+                    timestamped_key: timestamped_key.clone(),
+                });
+                continue;
+            };
+
+            if data.is_some_and(|data| !data.contains_key(timestamped_key)) {
+                report.inconsistencies.push(Inconsistency::MissingValue {
+                    key: key.clone(),
+                    timestamped_key: timestamped_key.clone(),
+                    file_name,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn keys_count_by_location(&self) -> KeyLocationCounts {
+        let memtable = self
+            .index
+            .values()
+            .filter(|timestamped_key| self.is_in_log_file(timestamped_key))
+            .count();
+
+        KeyLocationCounts {
+            memtable,
+            data_files: self.index.len() - memtable,
+        }
+    }
+
+    fn pending_deletes(&self) -> io::Result<usize> {
+        let keys_to_delete: HashSet<String> = self.get_keys_to_delete()?.into_iter().collect();
+        Ok(keys_to_delete.len())
+    }
+
+    fn size_on_disk(&self) -> io::Result<u64> {
+        let mut paths = vec![self.index_file_path.clone(), self.del_file_path.clone()];
+        for filename in self
+            .folder
+            .get_files_with_extensions(&self.db_path, vec![LOG_FILE_EXT, DATA_FILE_EXT])?
+        {
+            paths.push(self.db_path.join(filename));
+        }
+
+        let mut size = 0;
+        for path in paths {
+            size += self.folder.get_file_size_bytes(&path)?;
+        }
+
+        Ok(size)
+    }
+
+    fn fragmentation(&self) -> io::Result<Vec<(String, f64)>> {
+        let keys_to_delete: HashSet<String> = self.get_keys_to_delete()?.into_iter().collect();
+        let mut filenames = self
+            .folder
+            .get_files_with_extensions(&self.db_path, vec![LOG_FILE_EXT, DATA_FILE_EXT])?;
+        filenames.sort();
+
+        let mut ratios = Vec::with_capacity(filenames.len());
+        for filename in filenames {
+            let content = self.folder.read_to_string(self.db_path.join(&filename))?;
+            let deleted_bytes: usize = utils::extract_key_values_from_str(&content)?
+                .iter()
+                .filter(|(timestamped_key, _)| keys_to_delete.contains(*timestamped_key))
+                .map(|(timestamped_key, value)| {
+                    timestamped_key.len()
+                        + KEY_VALUE_SEPARATOR.len()
+                        + value.len()
+                        + TOKEN_SEPARATOR.len()
+                })
+                .sum();
+
+            let ratio = if content.is_empty() {
+                0.0
+            } else {
+                deleted_bytes as f64 / content.len() as f64
+            };
+            ratios.push((filename, ratio));
+        }
+
+        Ok(ratios)
+    }
+
+    fn stats(&self) -> io::Result<Stats> {
+        Ok(Stats {
+            keys: self.index.len(),
+            data_files: self.data_files.len(),
+            size_on_disk_bytes: self.size_on_disk()?,
+            pending_deletes: self.pending_deletes()?,
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+        })
+    }
+
+    fn iter_data_file(&self, filename: &str) -> io::Result<Vec<(String, String)>> {
+        let content = self.folder.read_to_string(self.db_path.join(filename))?;
+        let mut entries: Vec<(String, String)> = utils::extract_key_values_from_str(&content)?
+            .into_iter()
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        Ok(entries)
+    }
+
+    fn recent_entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(&String, &String)> = self
+            .index
+            .iter()
+            .filter(|(_, timestamped_key)| self.is_in_log_file(timestamped_key))
+            .collect();
+        entries.sort_by(|(_, a), (_, b)| utils::compare_timestamps(a, b));
+
+        entries
+            .into_iter()
+            .filter_map(|(key, timestamped_key)| {
+                self.memtable
+                    .get(timestamped_key)
+                    .and_then(|value| self.decode_value(value).ok())
+                    .map(|value| (key.clone(), value))
+            })
+            .collect()
+    }
+
+    fn shrink_memory(&mut self) {
+        self.index.shrink_to_fit();
+        self.memtable.shrink_to_fit();
+        self.cache.data.shrink_to_fit();
+        for cache in self.cached_ranges.iter_mut() {
+            cache.data.shrink_to_fit();
+        }
+    }
+
+    fn merge_from(&mut self, other_db_path: &str, policy: MergePolicy) -> io::Result<usize> {
+        let mut other = Store::new(other_db_path, self.max_file_size_kb);
+        other.set_vacuum_on_load(false);
+        other.set_track_last_modified(self.track_last_modified);
+        #[cfg(feature = "encryption")]
+        other.set_encryption_key(self.encryption_key);
+
+        if !other.exists_on_disk() {
+            return Err(NotADatabaseError.into());
+        }
+
+        other.load_without_changes()?;
+        let pairs = other
+            .filter(&|key, _| policy == MergePolicy::TakeTheirs || !self.index.contains_key(key))?;
+
+        self.set_many_from_iter(&mut pairs.into_iter())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.copy_data_files_for_snapshots(&self.data_files);
+        self.index.clear();
+        self.unflushed_sets_count = 0;
+        self.clear_disk()?;
+        self.load()
+    }
+
+    fn clear_async(&mut self) -> io::Result<JoinHandle<io::Result<()>>> {
+        let trash_path = self.get_trash_path()?;
+        self.copy_data_files_for_snapshots(&self.data_files);
+        self.index.clear();
+        self.unflushed_sets_count = 0;
+        let task = self
+            .folder
+            .remove_dir_all_in_background(&self.db_path, trash_path)?;
+        self.load()?;
+        Ok(task)
+    }
+
+    fn vacuum(&mut self) -> io::Result<()> {
+        self.ensure_not_degraded()?;
+        self.delete_keys_marked_for_deletion_from_disk()?;
+        self.coalesce_small_data_files()
+    }
+}
+
+impl Store {
+    /// Deletes the key-value pairs listed in the del file from the log file and the data files,
+    /// and then clears the del file
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_keys_to_delete], [Folder::delete_key_values_from_file] and [Folder::write]
+    fn delete_keys_marked_for_deletion_from_disk(&mut self) -> io::Result<()> {
+        let file_exts_to_vacuum = vec![LOG_FILE_EXT, DATA_FILE_EXT];
+        let keys_to_delete = self.get_keys_to_delete()?;
+
+        if keys_to_delete.len() == 0 {
+            return Ok(());
+        }
+
+        let data_files_to_vacuum: Vec<String> = keys_to_delete
+            .iter()
+            .filter_map(|key| self.get_timestamp_range_for_key(key))
+            .map(|(start, _)| start)
+            .collect();
+        self.copy_data_files_for_snapshots(&data_files_to_vacuum);
+
+        let paths_to_vacuum: Vec<PathBuf> = self
+            .folder
+            .get_files_with_extensions(&self.db_path, file_exts_to_vacuum)?
+            .into_iter()
+            .map(|filename| self.db_path.join(filename))
+            .collect();
+
+        self.folder
+            .delete_key_values_from_files(&paths_to_vacuum, &keys_to_delete)?;
+
+        // Clear del file only after all files are vacuumed
+        self.folder.write(&self.del_file_path, "")?;
+        self.keys_marked_for_deletion.clear();
+
+        Ok(())
+    }
+
+    /// Merges each run of adjacent data files smaller than `min_data_file_kb` into the first
+    /// data file in the run, as long as the merged data file stays smaller than `max_file_size_kb`.
+    /// The keys in the other data files of the run then fall in the range of the first data file.
+    /// It does nothing if `min_data_file_kb` is not set.
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_files_with_extensions], [Folder::get_file_size] and [Store::merge_data_files]
+    fn coalesce_small_data_files(&mut self) -> io::Result<()> {
+        let min_data_file_kb = match self.min_data_file_kb {
+            Some(min_data_file_kb) => min_data_file_kb,
+            None => return Ok(()),
+        };
+
+        let data_file_ext = format!(".{}", DATA_FILE_EXT);
+        let mut data_files: Vec<String> = self
+            .folder
+            .get_files_with_extensions(&self.db_path, vec![DATA_FILE_EXT])?
+            .iter()
+            .map(|filename| filename.trim_end_matches(&data_file_ext).to_string())
+            .collect();
+        data_files.sort_by(|a, b| utils::compare_timestamps(a, b));
+
+        let mut removed_data_files: Vec<String> = vec![];
+        let mut run: Vec<String> = vec![];
+        let mut run_size_kb = 0.0;
+
+        for data_file in data_files {
+            let path = self.db_path.join(format!("{}{}", data_file, data_file_ext));
+            let size_kb = self.folder.get_file_size(&path)?;
+            let is_small = size_kb < min_data_file_kb;
+
+            if !is_small || run_size_kb + size_kb >= self.max_file_size_kb {
+                removed_data_files.extend(self.merge_data_files(&run)?);
+                run.clear();
+                run_size_kb = 0.0;
+            }
+
+            if is_small {
+                run.push(data_file);
+                run_size_kb += size_kb;
+            }
+        }
+        removed_data_files.extend(self.merge_data_files(&run)?);
+
+        if !removed_data_files.is_empty() {
+            self.data_files
+                .retain(|data_file| !removed_data_files.contains(data_file));
+            self.clear_cache();
+        }
+
+        Ok(())
+    }
+
+    /// Merges the contents of the given data files into the first of them, and deletes the rest,
+    /// returning the names of the deleted data files
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::read_to_string], [Folder::persist_map_data_to_file], [Folder::sync]
+    /// and [Folder::remove_file]
+    fn merge_data_files(&mut self, data_files: &[String]) -> io::Result<Vec<String>> {
+        if data_files.len() < 2 {
+            return Ok(vec![]);
+        }
+
+        self.copy_data_files_for_snapshots(data_files);
+
+        let paths: Vec<PathBuf> = data_files
+            .iter()
+            .map(|data_file| {
+                self.db_path
+                    .join(format!("{}.{}", data_file, DATA_FILE_EXT))
+            })
+            .collect();
+        let mut data: HashMap<String, String> = Default::default();
+        for path in &paths {
+            let content = self.folder.read_to_string(path)?;
+            data.extend(utils::extract_key_values_from_str(&content)?);
+        }
+
+        // the merged data is saved before the other files are deleted so that no data is lost
+        self.folder.persist_map_data_to_file(&data, &paths[0])?;
+        if self.fsync != FsyncPolicy::Never {
+            self.folder.sync(&paths[0])?;
+        }
+
+        for path in &paths[1..] {
+            self.folder.remove_file(path)?;
+        }
+
+        Ok(data_files[1..].to_vec())
+    }
+
+    /// Creates a new instance of Store
+    ///
+    /// `db_path` is the path to the folder to contain the database files.
+    ///
+    /// `max_file_size_kb` is the maximum size in kilobytes that the data files can be. Beyond that,
+    ///
+    /// # Errors
+    /// - [io::Error] I/O errors e.g file permissions, missing files in case the `db_path` database folder
+    /// is not accessible
+    ///
+    /// [io::Error]: std::io::Error
+    pub(crate) fn new<P: AsRef<Path>>(db_path: P, max_file_size_kb: f64) -> Store {
+        let db_path = db_path.as_ref().to_path_buf();
+        let del_file_path = db_path.join(DEL_FILENAME);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+
+        Store {
+            db_path,
+            max_file_size_kb,
+            folder: Folder::Disk,
+            max_log_entries: None,
+            vacuum_on_load: true,
+            fsync: FsyncPolicy::Never,
+            max_value_size_bytes: None,
+            split_data_files: false,
+            resilient_reads: false,
+            cache_memory_budget_kb: None,
+            min_data_file_kb: None,
+            flush_every_n: None,
+            track_last_modified: false,
+            retry_policy: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            roll_hook: None,
+            load_progress_hook: None,
+            unflushed_sets_count: 0,
+            last_read_was_cached: false,
+            cache_hits: 0,
+            cache_misses: 0,
+            is_degraded: false,
+            clock: Box::new(SystemClock),
+            last_timestamp: 0,
+            cache: Cache::new_empty(),
+            cached_ranges: Default::default(),
+            snapshots: vec![],
+            memtable: Default::default(),
+            index: Default::default(),
+            keys_marked_for_deletion: Default::default(),
+            data_files: vec![],
+            current_log_file: "".to_string(),
+            current_log_file_path: PathBuf::new(),
+            del_file_path,
+            index_file_path,
+        }
+    }
+
+    /// Sets the [in_memory] option, which should be set before the store is loaded
+    ///
+    /// [in_memory]: crate::options::Options::in_memory
+    pub(crate) fn set_in_memory(&mut self, in_memory: bool) {
+        self.folder = if in_memory {
+            Folder::Memory(Default::default())
+        } else {
+            Folder::Disk
+        };
+    }
+
+    /// Sets the [vacuum_on_load] option
+    ///
+    /// [vacuum_on_load]: crate::options::Options::vacuum_on_load
+    pub(crate) fn set_vacuum_on_load(&mut self, vacuum_on_load: bool) {
+        self.vacuum_on_load = vacuum_on_load;
+    }
+
+    /// Sets the [fsync] option
+    ///
+    /// [fsync]: crate::options::Options::fsync
+    pub(crate) fn set_fsync(&mut self, fsync: FsyncPolicy) {
+        self.fsync = fsync;
+    }
+
+    /// Sets the [max_value_size_bytes] option
+    ///
+    /// [max_value_size_bytes]: crate::options::Options::max_value_size_bytes
+    pub(crate) fn set_max_value_size_bytes(&mut self, max_value_size_bytes: Option<usize>) {
+        self.max_value_size_bytes = max_value_size_bytes;
+    }
+
+    /// Sets the [split_data_files] option
+    ///
+    /// [split_data_files]: crate::options::Options::split_data_files
+    pub(crate) fn set_split_data_files(&mut self, split_data_files: bool) {
+        self.split_data_files = split_data_files;
+    }
+
+    /// Sets the [resilient_reads] option
+    ///
+    /// [resilient_reads]: crate::options::Options::resilient_reads
+    pub(crate) fn set_resilient_reads(&mut self, resilient_reads: bool) {
+        self.resilient_reads = resilient_reads;
+    }
+
+    /// Sets the [cache_memory_budget_kb] option
+    ///
+    /// [cache_memory_budget_kb]: crate::options::Options::cache_memory_budget_kb
+    pub(crate) fn set_cache_memory_budget_kb(&mut self, cache_memory_budget_kb: Option<f64>) {
+        self.cache_memory_budget_kb = cache_memory_budget_kb;
+    }
+
+    /// Sets the [min_data_file_kb] option
+    ///
+    /// [min_data_file_kb]: crate::options::Options::min_data_file_kb
+    pub(crate) fn set_min_data_file_kb(&mut self, min_data_file_kb: Option<f64>) {
+        self.min_data_file_kb = min_data_file_kb;
+    }
+
+    /// Sets the [track_last_modified] option
+    ///
+    /// [track_last_modified]: crate::options::Options::track_last_modified
+    pub(crate) fn set_track_last_modified(&mut self, track_last_modified: bool) {
+        self.track_last_modified = track_last_modified;
+    }
+
+    /// Sets the [retry_policy] option
+    ///
+    /// [retry_policy]: crate::options::Options::retry_policy
+    pub(crate) fn set_retry_policy(&mut self, retry_policy: Option<RetryPolicy>) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Sets the [encryption_key] option
+    ///
+    /// [encryption_key]: crate::options::Options::encryption_key
+    #[cfg(feature = "encryption")]
+    pub(crate) fn set_encryption_key(&mut self, encryption_key: Option<[u8; 32]>) {
+        self.encryption_key = encryption_key;
+    }
+
+    /// Sets the [flush_every_n] option
+    ///
+    /// [flush_every_n]: crate::options::Options::flush_every_n
+    pub(crate) fn set_flush_every_n(&mut self, flush_every_n: Option<usize>) {
+        self.flush_every_n = flush_every_n;
+    }
+
+    /// Sets the [max_log_entries] option
+    ///
+    /// [max_log_entries]: crate::options::Options::max_log_entries
+    pub(crate) fn set_max_log_entries(&mut self, max_log_entries: Option<usize>) {
+        self.max_log_entries = max_log_entries;
+    }
+
+    /// Sets the [roll_hook] option
+    ///
+    /// [roll_hook]: crate::options::Options::roll_hook
+    pub(crate) fn set_roll_hook(&mut self, hook: Option<RollHook>) {
+        self.roll_hook = hook;
+    }
+
+    /// Sets the [load_progress_hook] option
+    ///
+    /// [load_progress_hook]: crate::options::Options::load_progress_hook
+    pub(crate) fn set_load_progress_hook(&mut self, hook: Option<LoadProgressHook>) {
+        self.load_progress_hook = hook;
+    }
+
+    /// Sets the clock from which the timestamps for new log files and new timestamped keys
+    /// are got. By default, the system clock is used.
+    #[cfg(test)]
+    pub(crate) fn set_clock(&mut self, clock: Box<dyn Clock + Send>) {
+        self.clock = clock;
+    }
+
+    /// Checks whether the database folder already holds a ckydb database
+    /// i.e. it has both the index file and the del file
+    // #[inline]
+    pub(crate) fn exists_on_disk(&self) -> bool {
+        self.folder.is_file(&self.index_file_path) && self.folder.is_file(&self.del_file_path)
+    }
+
+    /// Checks whether the database folder is a symbolic link to another folder
+    // #[inline]
+    pub(crate) fn is_db_path_symlink(&self) -> bool {
+        self.folder.is_symlink(&self.db_path)
+    }
+
+    /// Creates a new index file if there is no index file in the database folder
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::create_file_if_not_exist]
+    // #[inline]
+    fn create_index_file_if_not_exists(&mut self) -> io::Result<()> {
+        self.folder.create_file_if_not_exist(&self.index_file_path)
+    }
+
+    /// Creates a new del file if there is no del file in the database folder
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::create_file_if_not_exist]
+    // #[inline]
+    fn create_del_file_if_not_exists(&mut self) -> io::Result<()> {
+        self.folder.create_file_if_not_exist(&self.del_file_path)
+    }
+
+    /// Renames any log file or data file named after a timestamp that is not zero-padded,
+    /// as created before timestamps were [zero-padded], to the zero-padded timestamp.
+    /// The timestamped keys in the files are left as they are since they are compared
+    /// by the numeric values of their timestamps
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_files_with_extensions] and [Folder::rename]
+    ///
+    /// [zero-padded]: utils::format_timestamp
+    fn pad_legacy_file_names(&mut self) -> io::Result<()> {
+        let extensions = vec![LOG_FILE_EXT, DATA_FILE_EXT];
+        let files = self
+            .folder
+            .get_files_with_extensions(&self.db_path, extensions)?;
+
+        for filename in files {
+            let timestamp = match filename.rsplit_once('.') {
+                Some((timestamp, ext)) => timestamp.parse::<u128>().map(|ts| (ts, ext)),
+                None => continue,
+            };
+
+            if let Ok((timestamp, ext)) = timestamp {
+                let padded_filename = format!("{}.{}", utils::format_timestamp(timestamp), ext);
+                if padded_filename != filename {
+                    self.folder.rename(
+                        self.db_path.join(&filename),
+                        self.db_path.join(&padded_filename),
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new log file if there is no .log file in the database folder
+    ///
+    /// The log file may also be missing if the process crashed right after the log file
+    /// was rolled into a data file, so the new log file is always made newer than
+    /// all the timestamped keys in the latest data file.
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::create_file_if_not_exist], [Store::bump_last_timestamp_past_data_files]
+    /// and [Store::create_new_log_file]
+    // #[inline]
+    fn create_log_file_if_not_exists(&mut self) -> io::Result<()> {
+        let extensions = vec![LOG_FILE_EXT];
+        let log_files = self
+            .folder
+            .get_files_with_extensions(&self.db_path, extensions)?;
+
+        if log_files.len() > 0 {
+            self.current_log_file_path = self.db_path.join(&log_files[0]);
+            return Ok(());
+        }
+
+        self.bump_last_timestamp_past_data_files()?;
+        self.create_new_log_file()
+    }
+
+    /// Ensures that new timestamps are newer than the latest data file and
+    /// all the timestamped keys in it, even if the clock is behind them
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_files_with_extensions], [Folder::read_to_string]
+    /// and [utils::extract_key_values_from_str]
+    fn bump_last_timestamp_past_data_files(&mut self) -> io::Result<()> {
+        let data_files = self
+            .folder
+            .get_files_with_extensions(&self.db_path, vec![DATA_FILE_EXT])?;
+        let latest_data_file = data_files
+            .iter()
+            .filter_map(|filename| {
+                let timestamp = filename.trim_end_matches(&format!(".{}", DATA_FILE_EXT));
+                timestamp.parse::<u128>().ok().map(|ts| (ts, filename))
+            })
+            .max();
+
+        if let Some((data_file_timestamp, filename)) = latest_data_file {
+            let content = self.folder.read_to_string(self.db_path.join(filename))?;
+            let data = utils::extract_key_values_from_str(&content)?;
+            let latest_key_timestamp = data
+                .keys()
+                .filter_map(|timestamped_key| timestamped_key.split_once('-'))
+                .filter_map(|(timestamp, _)| timestamp.parse::<u128>().ok())
+                .max()
+                .unwrap_or(0);
+
+            self.last_timestamp = self
+                .last_timestamp
+                .max(data_file_timestamp)
+                .max(latest_key_timestamp);
+        }
+
+        Ok(())
+    }
+
+    /// loads the attributes that depend on the things in the folder, returning the number
+    /// of files in the folder. The load progress is reported for every file but the index file,
+    /// which is reported once the index is loaded
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_file_names_in_folder]
+    fn load_file_props_from_disk(&mut self) -> io::Result<usize> {
+        self.data_files.clear();
+
+        let files_in_folder = self.folder.get_file_names_in_folder(&self.db_path)?;
+        let total_files = files_in_folder.len();
+        let mut files_processed = 0;
+
+        for filename in files_in_folder {
+            if filename != INDEX_FILENAME {
+                files_processed += 1;
+                self.report_load_progress(files_processed, total_files);
+            }
+
+            let parts: Vec<&str> = filename.rsplitn(2, ".").collect();
+            if parts.len() < 2 {
+                continue;
+            }
+
+            let ext: &str = parts[0];
+            let filename: &str = parts[1];
+
+            if ext == LOG_FILE_EXT {
+                self.current_log_file = filename.to_string();
+                // new timestamps should never be older than the current log file
+                let log_file_timestamp = self.current_log_file.parse().unwrap_or(0);
+                self.last_timestamp = self.last_timestamp.max(log_file_timestamp);
+            } else if ext == DATA_FILE_EXT {
+                self.data_files.push(filename.to_string())
+            }
+        }
+
+        self.data_files
+            .sort_by(|a, b| utils::compare_timestamps(a, b));
+
+        Ok(total_files)
+    }
+
+    /// Runs the load progress hook, if any, with the files processed so far and the total files
+    // #[inline]
+    fn report_load_progress(&self, files_processed: usize, total_files: usize) {
+        if let Some(hook) = &self.load_progress_hook {
+            hook.call(files_processed, total_files);
+        }
+    }
+
+    /// Loads the store from the files already in the database folder without creating, renaming
+    /// or vacuuming any of them, unlike [Storage::load]. A missing log file is treated as
+    /// an empty one named with a new timestamp, so that every saved key is looked up in the data files
+    ///
+    /// # Errors
+    ///
+    /// See [Store::load_file_props_from_disk], [Store::load_index_from_disk]
+    /// and [Store::load_memtable_from_disk]
+    fn load_without_changes(&mut self) -> io::Result<()> {
+        self.load_file_props_from_disk()?;
+        self.load_index_from_disk()?;
+
+        if self.current_log_file.is_empty() {
+            self.current_log_file = self.get_new_timestamp_str()?;
+        } else {
+            self.current_log_file_path = self
+                .db_path
+                .join(format!("{}.{}", self.current_log_file, LOG_FILE_EXT));
+            self.load_memtable_from_disk()?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the index from the index file, and the keys marked for deletion from the del file
+    ///
+    /// # Error
+    ///
+    /// See [Folder::read_to_string], [utils::extract_key_values_from_str]
+    /// and [Store::get_keys_to_delete]
+    // #[inline]
+    fn load_index_from_disk(&mut self) -> io::Result<()> {
+        let content = self.folder.read_to_string(&self.index_file_path)?;
+        self.index = utils::extract_key_values_from_str(&content)?;
+        self.keys_marked_for_deletion = self.get_keys_to_delete()?.into_iter().collect();
+        Ok(())
+    }
+
+    /// Checks that the values saved in the store can be decrypted with the encryption key, if any,
+    /// by decrypting the value of one of the keys, so that a wrong key is caught on loading
+    /// rather than on every read. Values that cannot be read at all are left to the reads to report
+    ///
+    /// # Errors
+    ///
+    /// It will return a [WrongEncryptionKeyError] if the value cannot be decrypted
+    ///
+    /// [WrongEncryptionKeyError]: crate::errors::WrongEncryptionKeyError
+    #[cfg(feature = "encryption")]
+    fn verify_encryption_key(&mut self) -> io::Result<()> {
+        if self.encryption_key.is_none() {
+            return Ok(());
+        }
+
+        let timestamped_key = match self.memtable.keys().next().or(self.index.values().next()) {
+            Some(timestamped_key) => timestamped_key.clone(),
+            None => return Ok(()),
+        };
+
+        match self.get_stored_value_for_key(&timestamped_key) {
+            Ok(value) if self.decode_value(&value).is_err() => Err(WrongEncryptionKeyError.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Loads the memtable from the log file, leaving out the values of keys marked for deletion,
+    /// which remain in the log file until the next vacuum
+    ///
+    /// # Error
+    ///
+    /// See [Folder::read_to_string] and [utils::extract_key_values_from_str]
+    // #[inline]
+    fn load_memtable_from_disk(&mut self) -> io::Result<()> {
+        let content = self.folder.read_to_string(&self.current_log_file_path)?;
+        self.memtable = utils::extract_key_values_from_str(&content)?;
+        self.memtable
+            .retain(|key, _| !self.keys_marked_for_deletion.contains(key));
+        Ok(())
+    }
+
+    /// Creates a new log file basing on the current timestamp
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_new_timestamp_str] and [Folder::create_file_if_not_exist]
+    fn create_new_log_file(&mut self) -> io::Result<()> {
+        let log_file_name = self.get_new_timestamp_str()?;
+        let log_file_path = self
+            .db_path
+            .join(format!("{}.{}", log_file_name, LOG_FILE_EXT));
+
+        self.folder.create_file_if_not_exist(&log_file_path)?;
+
+        // update struct's props
+        self.current_log_file = log_file_name;
+        self.current_log_file_path = log_file_path;
+        Ok(())
+    }
+
+    /// Reads the del file and gets the keys to be deleted.
+    /// Empty or malformed entries e.g. due to a corrupted del file are ignored
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::read_to_string]
+    // #[inline]
+    fn get_keys_to_delete(&self) -> io::Result<Vec<String>> {
+        let content = self.folder.read_to_string(&self.del_file_path)?;
+        Ok(utils::extract_tokens_from_str(&content)
+            .into_iter()
+            .filter(|key| utils::is_timestamped_key(key))
+            .collect())
+    }
+
+    /// Gets the timestamped key corresponding to the given key in the index
+    /// If there is none, it creates a new timestamped key and adds it to the index and the index file
+    ///
+    /// # Errors
+    ///
+    /// It will return a [CorruptedDataError] if it encounters any issues with creating timestamp,
+    /// or a [DegradedError] if it fails to add it to the index file, putting the store in
+    /// degraded mode
+    ///
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    /// [DegradedError]: crate::errors::DegradedError
+    fn get_timestamped_key(&mut self, key: &str) -> io::Result<String> {
+        if let Some(k) = self.index.get(key) {
+            return Ok(k.to_string());
+        }
+
+        let timestamp = self.get_new_timestamp_str()?;
+        let timestamped_key = format!("{}-{}", timestamp, key);
+        self.add_timestamped_key_to_index(key, &timestamped_key)?;
+
+        Ok(timestamped_key)
+    }
+
+    /// Adds the key and its timestamped key to the index and the index file
+    ///
+    /// # Errors
+    ///
+    /// It will return a [DegradedError] if it fails to add them to the index file, putting
+    /// the store in degraded mode. The key is then left out of the index
+    ///
+    /// [DegradedError]: crate::errors::DegradedError
+    fn add_timestamped_key_to_index(&mut self, key: &str, timestamped_key: &str) -> io::Result<()> {
+        let new_file_entry = utils::convert_key_values_to_str([(key, timestamped_key)]);
+
+        self.index
+            .insert(key.to_string(), timestamped_key.to_string());
+        if utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder
+                .append_to_file(&self.index_file_path, &new_file_entry)
+        })
+        .is_err()
+        {
+            // the index file can no longer be relied upon so writes are stopped altogether
+            self.index.remove(key);
+            self.is_degraded = true;
+            return Err(DegradedError.into());
+        }
+
+        Ok(())
+    }
+
+    /// Ensures the store is not in degraded mode before writing to it
+    ///
+    /// # Errors
+    /// - [DegradedError] wrapped in an [io::Error] in case the store is in degraded mode
+    ///
+    /// [io::Error]: std::io::Error
+    /// [DegradedError]: crate::errors::DegradedError
+    // #[inline]
+    fn ensure_not_degraded(&self) -> io::Result<()> {
+        if self.is_degraded {
+            return Err(DegradedError.into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns a new timestamp as a string, that is strictly greater than all timestamps
+    /// previously returned by this store, even if the system clock returns the same
+    /// nanosecond twice
+    ///
+    /// # Errors
+    ///
+    /// See [crate::clock::Clock::now]
+    // #[inline]
+    fn get_new_timestamp_str(&mut self) -> io::Result<String> {
+        let now = self.clock.now()?;
+        self.last_timestamp = utils::get_next_monotonic_timestamp(self.last_timestamp, now);
+        Ok(utils::format_timestamp(self.last_timestamp))
+    }
+
+    /// Removes the given keys from the index and the index file, and adds their
+    /// timestamped keys to the del file so that their values are removed from disk on the next vacuum.
+    /// Their values are removed from the memtable and the cache immediately to release memory,
+    /// but are kept in the files they are in till then so that they can be [undeleted].
+    /// All keys passed are expected to exist in the index.
+    ///
+    /// # Errors
+    ///
+    /// It will return a [DegradedError] if it fails to update the index file or the del file,
+    /// putting the store in degraded mode. See [Storage::flush] for the errors that can occur
+    /// on persisting buffered sets of the keys
+    ///
+    /// [undeleted]: Storage::undelete
+    /// [DegradedError]: crate::errors::DegradedError
+    fn mark_keys_for_deletion(&mut self, keys: &Vec<String>) -> io::Result<()> {
+        // buffered sets must reach the log file before their values are dropped from memory
+        let has_buffered_values = keys.iter().any(|key| {
+            self.index
+                .get(key)
+                .is_some_and(|timestamped_key| self.memtable.contains_key(timestamped_key))
+        });
+        if has_buffered_values {
+            self.flush()?;
+        }
+
+        let new_file_entries =
+            utils::convert_tokens_to_str(keys.iter().filter_map(|key| self.index.get(key)));
+
+        if utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder
+                .delete_key_values_from_file(&self.index_file_path, keys)
+        })
+        .and_then(|_| {
+            utils::retry_io(self.retry_policy.as_ref(), || {
+                self.folder
+                    .append_to_file(&self.del_file_path, &new_file_entries)
+            })
+        })
+        .is_err()
+        {
+            // the index and del files may now disagree so writes are stopped altogether
+            self.is_degraded = true;
+            return Err(DegradedError.into());
+        }
+
+        for key in keys {
+            if let Some(timestamped_key) = self.index.remove(key) {
+                self.memtable.remove(&timestamped_key);
+                self.cache.remove(&timestamped_key);
+                for cache in self.cached_ranges.iter_mut() {
+                    cache.remove(&timestamped_key);
+                }
+                self.keys_marked_for_deletion.insert(timestamped_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the value of a deleted timestamped key from the log file or the data file it was in,
+    /// returning `None` if it is no longer there. It is read from disk because deleted
+    /// key-value pairs are removed from the memtable and the cache
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::read_to_string]
+    fn get_value_for_deleted_key(&self, timestamped_key: &str) -> io::Result<Option<String>> {
+        let file_path = if self.is_in_log_file(timestamped_key) {
+            self.current_log_file_path.clone()
+        } else {
+            match self.get_timestamp_range_for_key(timestamped_key) {
+                Some((start, _)) => self.db_path.join(format!("{}.{}", start, DATA_FILE_EXT)),
+                None => return Ok(None),
+            }
+        };
+
+        let content = self.folder.read_to_string(&file_path)?;
+        let mut data = utils::extract_key_values_from_str(&content)?;
+        Ok(data.remove(timestamped_key))
+    }
+
+    /// Removes the key and timestamped key from the index
+    /// and the index file if it exists
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::delete_key_values_from_file]
+    // #[inline]
+    fn remove_timestamped_key_for_key_if_exists(&mut self, key: &str) -> io::Result<()> {
+        if let Some(_) = self.index.get(key) {
+            self.index.remove(key);
+            self.folder
+                .delete_key_values_from_file(&self.index_file_path, &vec![key.to_string()])?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the key value pair in memtable and log file if it is newer than log file
+    /// or in cache and in the corresponding dataFile if the key is old
+    ///
+    /// # Error
+    ///
+    /// See [Store::save_key_value_pair_to_memtable], [Store::load_cache_containing_key],
+    /// [Store::save_key_value_pair_to_cache]
+    // #[inline]
+    fn save_key_value_pair(&mut self, timestamped_key: &str, value: &str) -> io::Result<()> {
+        if self.is_in_log_file(timestamped_key) {
+            return self.save_key_value_pair_to_memtable(timestamped_key, value);
+        }
+
+        if !self.cache.is_in_range(timestamped_key) {
+            self.load_cache_containing_key(timestamped_key)?;
+        }
+
+        self.save_key_value_pair_to_cache(timestamped_key, value)
+    }
+
+    /// Deletes the given key and its value from
+    /// the index, the cache or the memtable, the log file or any data file
+    ///
+    /// # Errors
+    ///
+    /// See [Store::persist_cache_to_disk] and [Store::write_memtable_to_log_file]
+    // #[inline]
+    fn delete_key_value_pair_if_exists(&mut self, key: &str) -> io::Result<()> {
+        if self.cache.is_in_range(key) {
+            self.cache.remove(key);
+            return self.persist_cache_to_disk();
+        }
+
+        if self.is_in_log_file(key) {
+            self.memtable.remove(key);
+            return self.write_memtable_to_log_file();
+        }
+
+        Ok(())
+    }
+
+    /// Saves the key value pair to memtable and persists memtable
+    /// to current log file, unless the set is to be buffered till the next flush
+    ///
+    /// # Errors
+    ///
+    /// See [Storage::flush]
+    // #[inline]
+    fn save_key_value_pair_to_memtable(
+        &mut self,
+        timestamped_key: &str,
+        value: &str,
+    ) -> io::Result<()> {
+        self.memtable
+            .insert(timestamped_key.to_string(), value.to_string());
+        self.unflushed_sets_count += 1;
+
+        let is_buffered = self
+            .flush_every_n
+            .is_some_and(|n| self.unflushed_sets_count < n);
+        if !is_buffered {
+            return self.flush();
+        }
+
+        Ok(())
+    }
+
+    /// Persists the memtable to the current log file, rolling the log file if it is now too big
+    ///
+    /// # Errors
+    ///
+    /// See [Store::write_memtable_to_log_file], [Folder::sync] and [Store::roll_log_file_if_too_big]
+    // #[inline]
+    fn persist_memtable_to_disk(&mut self) -> io::Result<()> {
+        self.write_memtable_to_log_file()?;
+
+        if self.fsync == FsyncPolicy::OnEverySet {
+            self.folder.sync(&self.current_log_file_path)?;
+            self.folder.sync(&self.index_file_path)?;
+        }
+
+        self.roll_log_file_if_too_big()
+    }
+
+    /// Writes the memtable to the current log file, along with the key-value pairs of keys
+    /// marked for deletion already in it, which are kept there till the next vacuum
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_deleted_key_values_in_file] and [Folder::persist_map_data_to_file]
+    fn write_memtable_to_log_file(&mut self) -> io::Result<()> {
+        let mut data = self.get_deleted_key_values_in_file(&self.current_log_file_path, |key| {
+            self.is_in_log_file(key)
+        })?;
+        let data = if data.is_empty() {
+            &self.memtable
+        } else {
+            data.extend(self.memtable.clone());
+            &data
+        };
+
+        utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder
+                .persist_map_data_to_file(data, &self.current_log_file_path)
+        })
+    }
+
+    /// Gets the key-value pairs of keys marked for deletion in the file at `file_path`,
+    /// the file being read only if any of those keys `is_in_file`.
+    /// Their values are dropped from memory on deletion, so they have to be read back from the file
+    /// for them to be kept in it when it is rewritten
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::read_to_string] and [utils::extract_key_values_from_str]
+    fn get_deleted_key_values_in_file(
+        &self,
+        file_path: &Path,
+        is_in_file: impl Fn(&str) -> bool,
+    ) -> io::Result<HashMap<String, String>> {
+        if !self
+            .keys_marked_for_deletion
+            .iter()
+            .any(|key| is_in_file(key))
+        {
+            return Ok(Default::default());
+        }
+
+        let content = self.folder.read_to_string(file_path)?;
+        let mut data = utils::extract_key_values_from_str(&content)?;
+        data.retain(|key, _| self.keys_marked_for_deletion.contains(key));
+        Ok(data)
+    }
+
+    /// Checks that the key value pair can be set i.e. the key is not empty and the value
+    /// is not bigger than the maximum value size
+    ///
+    /// # Errors
+    ///
+    /// It will return an [InvalidKeyError] if the key is empty or a [ValueTooLargeError]
+    /// if the value is too big
+    // #[inline]
+    fn validate_key_value_pair(&self, key: &str, value: &str) -> io::Result<()> {
+        if key.is_empty() {
+            return Err(InvalidKeyError.into());
+        }
+
+        if self
+            .max_value_size_bytes
+            .is_some_and(|max_size| value.len() > max_size)
+        {
+            return Err(ValueTooLargeError.into());
+        }
+
+        Ok(())
+    }
+
+    /// Adds the key value pair to the index and the memtable without persisting either of them,
+    /// pushing any new key and its timestamped key onto `new_index_entries`.
+    /// Old keys are saved straight away to the cache and their data file.
+    ///
+    /// # Errors
+    ///
+    /// See [Store::validate_key_value_pair], [Store::get_new_timestamp_str] and [Store::save_key_value_pair]
+    fn stage_key_value_pair(
+        &mut self,
+        key: &str,
+        value: &str,
+        new_index_entries: &mut Vec<(String, String)>,
+    ) -> io::Result<()> {
+        self.validate_key_value_pair(key, value)?;
+        let value = &self.encode_value(value)?;
+
+        let timestamped_key = match self.index.get(key) {
+            Some(timestamped_key) => timestamped_key.clone(),
+            None => {
+                let timestamp = self.get_new_timestamp_str()?;
+                let timestamped_key = format!("{}-{}", timestamp, key);
+                new_index_entries.push((key.to_string(), timestamped_key.clone()));
+                self.index.insert(key.to_string(), timestamped_key.clone());
+                timestamped_key
+            }
+        };
+
+        if self.is_in_log_file(&timestamped_key) {
+            self.memtable.insert(timestamped_key, value.to_string());
+            return Ok(());
+        }
+
+        self.save_key_value_pair(&timestamped_key, value)
+    }
+
+    /// Saves the key value pair to cache and persists cache
+    /// to corresponding data file
+    ///
+    /// # Errors
+    ///
+    /// See [Store::persist_cache_to_disk]
+    // #[inline]
+    fn save_key_value_pair_to_cache(
+        &mut self,
+        timestamped_key: &str,
+        value: &str,
+    ) -> io::Result<()> {
+        self.cache.update(timestamped_key, value);
+        self.persist_cache_to_disk()?;
+        self.evict_cache_if_over_budget();
+        Ok(())
+    }
+
+    /// Evicts the least recently used ranges from the cache until it takes up no more memory
+    /// than the cache memory budget. The current range is evicted too if it alone exceeds the budget
+    fn evict_cache_if_over_budget(&mut self) {
+        let budget = match self.cache_memory_budget_kb {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let mut cache_size_kb = self.cache.get_size_kb()
+            + self
+                .cached_ranges
+                .iter()
+                .map(Cache::get_size_kb)
+                .sum::<f64>();
+
+        while cache_size_kb > budget {
+            match self.cached_ranges.pop_front() {
+                Some(cache) => cache_size_kb -= cache.get_size_kb(),
+                None => {
+                    self.cache = Cache::new_empty();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Empties the cache, evicting all its ranges
+    // #[inline]
+    fn clear_cache(&mut self) {
+        self.cache = Cache::new_empty();
+        self.cached_ranges.clear();
+    }
+
+    /// Checks whether the given key is in any range held in the cache
+    // #[inline]
+    fn is_in_cached_ranges(&self, key: &str) -> bool {
+        self.cache.is_in_range(key) || self.cached_ranges.iter().any(|c| c.is_in_range(key))
+    }
+
+    /// Loads the cache with data containing the timestampedKey, making it the current range.
+    /// The range is taken from the ranges held in the cache if it is there, otherwise it is read
+    /// from its data file, leaving out the values of keys marked for deletion, which remain
+    /// in the data file until the next vacuum. The range it replaces is kept in the cache
+    /// if there is a cache memory budget, to be evicted by [Store::evict_cache_if_over_budget]
+    ///
+    /// # Errors
+    ///
+    /// A [crate::errors::CorruptedDataError] will be returned if the key does not fall in
+    /// an of the ranges of timestamps represented by the data file names and the log file name.
+    /// Other errors may occur as seen in
+    /// [Folder::read_to_string] and [utils::extract_key_values_from_str]
+    // #[inline]
+    fn load_cache_containing_key(&mut self, key: &str) -> io::Result<()> {
+        let (start, end) = self
+            .get_timestamp_range_for_key(key)
+            .ok_or(CorruptedDataError)?;
+
+        let cached_range = self
+            .cached_ranges
+            .iter()
+            .position(|cache| cache.start == start && cache.end == end)
+            .and_then(|i| self.cached_ranges.remove(i));
+        let cache = match cached_range {
+            Some(cache) => cache,
+            None => {
+                // get data from disk
+                let file_path = self.db_path.join(format!("{}.{}", start, DATA_FILE_EXT));
+                let content_str = self.folder.read_to_string(&file_path)?;
+                let mut map_data = utils::extract_key_values_from_str(&content_str)?;
+                map_data.retain(|key, _| !self.keys_marked_for_deletion.contains(key));
+                // any range cached for the same data file before it changed is stale
+                self.cached_ranges.retain(|cache| cache.start != start);
+                Cache::new(map_data, &start, &end)
+            }
+        };
+
+        let previous_cache = std::mem::replace(&mut self.cache, cache);
+        if self.cache_memory_budget_kb.is_some() && !previous_cache.is_empty() {
+            self.cached_ranges.push_back(previous_cache);
+        }
+
+        Ok(())
+    }
+
+    /// Rolls the current log file if it has exceeded the maximum size it should have
+    /// or if the memtable has reached the maximum number of entries it should have
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_file_size] and [Store::roll_log_file]
+    fn roll_log_file_if_too_big(&mut self) -> io::Result<()> {
+        let log_file_size = self.folder.get_file_size(&self.current_log_file_path)?;
+        let has_too_many_entries = self
+            .max_log_entries
+            .is_some_and(|max_entries| self.memtable.len() >= max_entries);
+
+        if log_file_size >= self.max_file_size_kb || has_too_many_entries {
+            self.roll_log_file()?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames the current log file to a data file and creates a new log file,
+    /// clearing the memtable. The roll hook, if any, is then run with the new data file's name
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::rename], [Folder::sync] and [Store::create_new_log_file].
+    /// It will return a [RollHookError] if the roll hook fails
+    ///
+    /// [RollHookError]: crate::errors::RollHookError
+    fn roll_log_file(&mut self) -> io::Result<()> {
+        let new_data_filename = format!("{}.{}", self.current_log_file, DATA_FILE_EXT);
+        let new_data_file_path = self.db_path.join(&new_data_filename);
+        self.folder
+            .rename(&self.current_log_file_path, &new_data_file_path)?;
+
+        if self.fsync != FsyncPolicy::Never {
+            self.folder.sync(&new_data_file_path)?;
+            self.folder.sync(&self.index_file_path)?;
+        }
+
+        self.memtable.clear();
+        self.data_files.push(self.current_log_file.clone());
+        // endure the data files are sorted
+        self.data_files
+            .sort_by(|a, b| utils::compare_timestamps(a, b));
+        self.create_new_log_file()?;
+
+        if let Some(hook) = &self.roll_hook {
+            hook.call(&new_data_filename).map_err(|err| RollHookError {
+                data_file: new_data_filename.clone(),
+                message: err.to_string(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Persists the current cache to its corresponding data file, along with the key-value pairs
+    /// of keys marked for deletion already in it, which are kept there till the next vacuum.
+    /// The data file is first copied into the snapshots that read from it
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_deleted_key_values_in_file], [Folder::persist_map_data_to_file],
+    /// [Folder::sync] and [Store::split_cache_data_file_if_too_big]
+    // #[inline]
+    fn persist_cache_to_disk(&mut self) -> io::Result<()> {
+        self.copy_data_files_for_snapshots(std::slice::from_ref(&self.cache.start));
+        let data_file_path = self
+            .db_path
+            .join(format!("{}.{}", self.cache.start, DATA_FILE_EXT));
+        let mut data = self
+            .get_deleted_key_values_in_file(&data_file_path, |key| self.cache.is_in_range(key))?;
+        let data = if data.is_empty() {
+            &self.cache.data
+        } else {
+            data.extend(self.cache.data.clone());
+            &data
+        };
+
+        utils::retry_io(self.retry_policy.as_ref(), || {
+            self.folder.persist_map_data_to_file(data, &data_file_path)
+        })?;
+
+        if self.fsync == FsyncPolicy::OnEverySet {
+            self.folder.sync(&data_file_path)?;
+            self.folder.sync(&self.index_file_path)?;
+        }
+
+        if self.split_data_files {
+            self.split_cache_data_file_if_too_big()?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the data file of the current cache into two data files if it has exceeded the
+    /// maximum size it should have. The second file is named after the timestamp of the middle
+    /// timestamped key so that each key still falls in the range of the file that holds it.
+    /// The cache is left holding only the first file. Key-value pairs of keys marked for deletion
+    /// in the data file are kept in whichever file their keys fall in.
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::get_file_size], [Store::get_deleted_key_values_in_file],
+    /// [Folder::persist_map_data_to_file] and [Folder::sync]
+    fn split_cache_data_file_if_too_big(&mut self) -> io::Result<()> {
+        let start = self.cache.start.clone();
+        let data_file_path = self.db_path.join(format!("{}.{}", start, DATA_FILE_EXT));
+
+        if self.folder.get_file_size(&data_file_path)? < self.max_file_size_kb {
+            return Ok(());
+        }
+
+        let mut timestamped_keys: Vec<&String> = self.cache.data.keys().collect();
+        timestamped_keys.sort_by(|a, b| utils::compare_timestamps(a, b));
+        // keys in databases created before timestamps were zero-padded have unpadded timestamps
+        let new_start = timestamped_keys
+            .get(timestamped_keys.len() / 2)
+            .and_then(|key| key.split_once('-'))
+            .and_then(|(ts, _)| ts.parse::<u128>().ok())
+            .map(utils::format_timestamp);
+        let new_start = match new_start {
+            Some(new_start) => new_start,
+            None => return Ok(()),
+        };
+
+        if utils::compare_timestamps(&new_start, &start).is_le() {
+            return Ok(());
+        }
+
+        let is_in_first_half = |key: &String| utils::compare_timestamps(key, &new_start).is_lt();
+        let (deleted_first_half, deleted_second_half): (HashMap<_, _>, HashMap<_, _>) = self
+            .get_deleted_key_values_in_file(&data_file_path, |key| self.cache.is_in_range(key))?
+            .into_iter()
+            .partition(|(key, _)| is_in_first_half(key));
+        let (first_half, second_half): (HashMap<String, String>, HashMap<String, String>) = self
+            .cache
+            .data
+            .drain()
+            .partition(|(key, _)| is_in_first_half(key));
+        let new_data_file_path = self
+            .db_path
+            .join(format!("{}.{}", new_start, DATA_FILE_EXT));
+
+        let mut second_file_data = deleted_second_half;
+        second_file_data.extend(second_half);
+        self.folder
+            .persist_map_data_to_file(&second_file_data, &new_data_file_path)?;
+        let mut first_file_data = deleted_first_half;
+        first_file_data.extend(first_half.clone());
+        self.folder
+            .persist_map_data_to_file(&first_file_data, &data_file_path)?;
+
+        if self.fsync != FsyncPolicy::Never {
+            self.folder.sync(&new_data_file_path)?;
+            self.folder.sync(&data_file_path)?;
+        }
+
+        self.data_files.push(new_start.clone());
+        self.data_files
+            .sort_by(|a, b| utils::compare_timestamps(a, b));
+        self.cache = Cache::new(first_half, &start, &new_start);
+        Ok(())
+    }
+
+    /// Checks whether the given timestamped key belongs to the current log file
+    /// i.e. it is in the memtable or it is not older than the log file.
+    /// Keys written by the Go and Python implementations may have fewer digits than the log file
+    /// they are in since those compare timestamps as strings, so the memtable is checked first
+    // #[inline]
+    fn is_in_log_file(&self, timestamped_key: &str) -> bool {
+        self.memtable.contains_key(timestamped_key)
+            || utils::compare_timestamps(timestamped_key, &self.current_log_file).is_ge()
+    }
+
+    /// Returns the range of timestamps between which
+    /// the key lies. The timestamps are got from the names of the data files and the current log file
+    /// It will return None if there is no relevant timestamp range from the available data file names
+    /// and log file names
+    // #[inline]
+    fn get_timestamp_range_for_key(&self, key: &str) -> Option<(String, String)> {
+        utils::get_timestamp_range_for_key(&self.data_files, &self.current_log_file, key)
+    }
+
+    /// Gets the value corresponding to a given timestampedKey
+    ///
+    /// # Errors
+    ///
+    /// It will return [crate::errors::CorruptedDataError] if the data on disk is inconsistent
+    /// with what is expected in memory e.g. if unable to load cache from disk, or cache or memtable
+    /// don't contain the key yet they should contain it.
+    ///
+    /// Obviously [crate::errors::CorruptedDataError] has a very minute chance of happening
+    // #[inline]
+    fn get_value_for_key(&mut self, timestamped_key: &str) -> Result<String, CorruptedDataError> {
+        let value = self.get_stored_value_for_key(timestamped_key)?;
+        self.decode_value(&value)
+    }
+
+    /// Gets the value for the given timestamped key as it is saved in the memtable or
+    /// the data files, including any last-modified timestamp.
+    /// If the value is not in the data file it is expected in, all data files are scanned for it
+    /// in case reads are resilient
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_value_for_key]
+    fn get_stored_value_for_key(
+        &mut self,
+        timestamped_key: &str,
+    ) -> Result<String, CorruptedDataError> {
+        // values of keys marked for deletion are kept on disk till the next vacuum, yet hidden
+        if self.keys_marked_for_deletion.contains(timestamped_key) {
+            return Err(CorruptedDataError);
+        }
+
+        self.last_read_was_cached = true;
+
+        if self.is_in_log_file(timestamped_key) {
+            self.cache_hits += 1;
+            let value = self
+                .memtable
+                .get(timestamped_key)
+                .ok_or(CorruptedDataError)?;
+            return Ok(value.to_string());
+        }
+
+        if self.is_in_cached_ranges(timestamped_key) {
+            self.cache_hits += 1;
+        } else {
+            self.last_read_was_cached = false;
+            self.cache_misses += 1;
+        }
+
+        let is_loading_cache = !self.cache.is_in_range(timestamped_key);
+        if is_loading_cache && self.load_cache_containing_key(timestamped_key).is_err() {
+            return self.find_value_in_all_data_files(timestamped_key);
+        }
+
+        let value = match self.cache.get(timestamped_key) {
+            Some(value) => value.to_string(),
+            None => return self.find_value_in_all_data_files(timestamped_key),
+        };
+        if is_loading_cache {
+            self.evict_cache_if_over_budget();
+        }
+        Ok(value)
+    }
+
+    /// Scans every data file in the database folder for the given timestamped key,
+    /// skipping any data files that cannot be read. The data file the value is found in is not
+    /// loaded into the cache since its name need not match the range of timestamps it holds
+    /// e.g. if it was renamed
+    ///
+    /// # Errors
+    ///
+    /// It will return a [CorruptedDataError] if reads are not resilient, if the key is
+    /// marked for deletion or if the key is in none of the data files
+    fn find_value_in_all_data_files(
+        &self,
+        timestamped_key: &str,
+    ) -> Result<String, CorruptedDataError> {
+        if !self.resilient_reads {
+            return Err(CorruptedDataError);
+        }
+
+        if self.keys_marked_for_deletion.contains(timestamped_key) {
+            return Err(CorruptedDataError);
+        }
+
+        let filenames = self
+            .folder
+            .get_files_with_extensions(&self.db_path, vec![DATA_FILE_EXT])
+            .or(Err(CorruptedDataError))?;
+
+        filenames
+            .iter()
+            .filter_map(|filename| self.folder.read_to_string(self.db_path.join(filename)).ok())
+            .filter_map(|content| utils::extract_key_values_from_str(&content).ok())
+            .find_map(|mut data| data.remove(timestamped_key))
+            .ok_or(CorruptedDataError)
+    }
+
+    /// Encrypts the value if there is an encryption key, and then prefixes it with the current
+    /// time if the last-modified timestamps are tracked
+    ///
+    /// # Errors
+    ///
+    /// See [crate::clock::Clock::now]
+    // #[inline]
+    fn encode_value(&self, value: &str) -> io::Result<String> {
+        #[cfg(feature = "encryption")]
+        let value = &match &self.encryption_key {
+            Some(key) => crypto::encrypt_value(value, key)?,
+            None => value.to_string(),
+        };
+
+        if !self.track_last_modified {
+            return Ok(value.to_string());
+        }
+
+        let now = self.clock.now()?;
+        Ok(utils::encode_value_with_modified_timestamp(value, now))
+    }
+
+    /// Strips the last-modified timestamp off a saved value if the timestamps are tracked,
+    /// and then decrypts it if there is an encryption key
+    ///
+    /// # Errors
+    ///
+    /// It will return a [CorruptedDataError] in case the value cannot be decrypted
+    /// e.g. it was encrypted with a different key
+    ///
+    /// [CorruptedDataError]: crate::errors::CorruptedDataError
+    // #[inline]
+    fn decode_value(&self, value: &str) -> Result<String, CorruptedDataError> {
+        let value = if self.track_last_modified {
+            utils::decode_value_with_modified_timestamp(value).1
+        } else {
+            value
+        };
+
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            return crypto::decrypt_value(value, key);
+        }
+
+        Ok(value.to_string())
+    }
+
+    /// Calls `f` on every key in the index and its value, in the order in which the keys were created,
+    /// until `f` returns [ControlFlow::Break].
+    /// Keys are visited in the order of their timestamped keys so that each data file
+    /// is loaded into the cache at most once.
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_value_for_key]
+    fn for_each_entry<F: FnMut(&str, &str) -> ControlFlow<()>>(
+        &mut self,
+        mut f: F,
+    ) -> io::Result<()> {
+        let mut entries: Vec<(String, String)> = self
+            .index
+            .iter()
+            .map(|(key, timestamped_key)| (timestamped_key.clone(), key.clone()))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| utils::compare_timestamps(a, b));
+
+        for (timestamped_key, key) in entries {
+            let value = self.get_value_for_key(&timestamped_key)?;
+            if f(&key, &value).is_break() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a new path, next to the database folder, to which the database folder can be
+    /// moved aside so as to be deleted in the background
+    ///
+    /// # Errors
+    ///
+    /// See [Store::get_new_timestamp_str]
+    fn get_trash_path(&mut self) -> io::Result<PathBuf> {
+        let timestamp = self.get_new_timestamp_str()?;
+        let db_folder_name = self.db_path.file_name().unwrap_or_default();
+        let trash_folder_name =
+            format!("{}.cleared-{}", db_folder_name.to_string_lossy(), timestamp);
+        Ok(self.db_path.with_file_name(trash_folder_name))
+    }
+
+    /// Copies the current contents of the given data files into the snapshots still alive that
+    /// read from them and have no copies of them yet, before the data files are rewritten or removed.
+    /// Data files that cannot be read are left for the snapshots to read from disk
+    fn copy_data_files_for_snapshots(&self, data_files: &[String]) {
+        for data_file_copies in self.snapshots.iter().filter_map(Weak::upgrade) {
+            let mut data_file_copies = data_file_copies.lock().expect("lock data file copies");
+            for data_file in data_files {
+                if let Some(copy @ None) = data_file_copies.get_mut(data_file) {
+                    let file_path = self
+                        .db_path
+                        .join(format!("{}.{}", data_file, DATA_FILE_EXT));
+                    *copy = self.folder.read_to_string(file_path).ok();
+                }
+            }
+        }
+    }
+
+    /// Deletes all files in the database folder
+    ///
+    /// # Errors
+    ///
+    /// See [Folder::remove_dir_all]
+    // #[inline]
+    fn clear_disk(&mut self) -> io::Result<()> {
+        self.folder.remove_dir_all(&self.db_path)
+    }
+}
+
+/// Checks whether the error is a [RollHookError] wrapped in an [io::Error]
+///
+/// [io::Error]: std::io::Error
+/// [RollHookError]: crate::errors::RollHookError
+// #[inline]
+fn is_roll_hook_error(err: &io::Error) -> bool {
+    err.get_ref()
+        .is_some_and(|inner| inner.is::<RollHookError>())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cache::{Cache, Caching};
+    use crate::clock::MockClock;
+    use crate::constants::{DEL_FILENAME, INDEX_FILENAME, KEY_VALUE_SEPARATOR, TOKEN_SEPARATOR};
+    use crate::options::{FsyncPolicy, LoadProgressHook, RollHook};
+    use crate::store::{
+        Inconsistency, KeyLocationCounts, KeyStatus, MergePolicy, Stats, Storage, Store,
+    };
+    use crate::utils;
+    use serial_test::serial;
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const DB_PATH: &str = "test_store_db";
+    const MAX_FILE_SIZE_KB: f64 = 320.0 / 1024.0;
+    const LOG_FILENAME: &str = "01655375171402014000.log";
+    const DATA_FILES: [&str; 2] = ["01655375120328185000.cky", "01655375120328186000.cky"];
+    const EMPTY_LIST: Vec<String> = vec![];
+
+    #[test]
+    #[serial]
     fn load_updates_memory_props_from_data_on_disk() {
         let expected_cache = Cache::new_empty();
-        let expected_index = HashMap::from(
-            [
-                ("cow", "1655375120328185000-cow"),
-                ("dog", "1655375120328185100-dog"),
-                ("goat", "1655404770518678-goat"),
-                ("hen", "1655404670510698-hen"),
-                ("pig", "1655404770534578-pig"),
-                ("fish", "1655403775538278-fish"),
-            ]
-            .map(|(k, v)| (k.to_string(), v.to_string())),
+        let expected_index = HashMap::from(
+            [
+                ("cow", "1655375120328185000-cow"),
+                ("dog", "1655375120328185100-dog"),
+                ("goat", "1655404770518678000-goat"),
+                ("hen", "1655404670510698000-hen"),
+                ("pig", "1655404770534578000-pig"),
+                ("fish", "1655403775538278000-fish"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        let expected_memtable = HashMap::from(
+            [
+                ("1655404770518678000-goat", "678 months"),
+                ("1655404670510698000-hen", "567 months"),
+                ("1655404770534578000-pig", "70 months"),
+                ("1655403775538278000-fish", "8990 months"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        let expected_data_files = DATA_FILES
+            .map(|filename| filename.trim_end_matches(".cky").to_string())
+            .to_vec();
+        let expected_current_log_file = LOG_FILENAME.trim_end_matches(".log").to_string();
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let db_path = Path::new(DB_PATH);
+        let log_file_path = db_path.join(LOG_FILENAME);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+        let del_file_path = db_path.join(DEL_FILENAME);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        assert_eq!(expected_cache, store.cache);
+        assert_eq!(expected_memtable, store.memtable);
+        assert_eq!(expected_index, store.index);
+        assert_eq!(expected_data_files, store.data_files);
+        assert_eq!(expected_current_log_file, store.current_log_file);
+        assert_eq!(log_file_path, store.current_log_file_path);
+        assert_eq!(index_file_path, store.index_file_path);
+        assert_eq!(del_file_path, store.del_file_path);
+    }
+
+    #[test]
+    #[serial]
+    fn load_creates_db_folder_with_del_and_index_files_if_not_exist() {
+        let expected_cache = Cache::new_empty();
+        let mut expected_files = [DEL_FILENAME, INDEX_FILENAME].map(String::from).to_vec();
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let db_path = Path::new(DB_PATH);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+        let del_file_path = db_path.join(DEL_FILENAME);
+        let empty_map: HashMap<String, String> = Default::default();
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        let current_log_filename = format!("{}.log", store.current_log_file);
+        expected_files.push(current_log_filename.clone());
+        let expected_log_file_path = OsString::from(Path::new(DB_PATH).join(current_log_filename));
+        let mut actual_files =
+            utils::get_file_names_in_folder(DB_PATH).expect("get files in db folder");
+
+        actual_files.sort();
+        expected_files.sort();
+
+        assert_eq!(expected_cache, store.cache);
+        assert_ne!("".to_string(), store.current_log_file);
+        assert_eq!(empty_map, store.index);
+        assert_eq!(empty_map, store.memtable);
+        assert_eq!(EMPTY_LIST, store.data_files);
+        assert_eq!(expected_files, actual_files);
+        assert_eq!(index_file_path, store.index_file_path);
+        assert_eq!(expected_log_file_path, store.current_log_file_path);
+        assert_eq!(del_file_path, store.del_file_path);
+    }
+
+    #[test]
+    #[serial]
+    fn reload_picks_up_changes_made_on_disk_by_another_store() {
+        let (key, value) = ("New key", "foo");
+        let mut writer = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let mut reader = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        writer.load().expect("loads writer store");
+        reader.load().expect("loads reader store");
+        writer
+            .set(key, value)
+            .unwrap_or_else(|_| panic!("set key: {}, value: {}", key, value));
+
+        match reader.get(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+
+        reader.reload().expect("reloads reader store");
+
+        assert_eq!(value, reader.get(key).unwrap());
+        assert_eq!(writer.index, reader.index);
+        assert_eq!(writer.memtable, reader.memtable);
+        assert_eq!(writer.current_log_file_path, reader.current_log_file_path);
+    }
+
+    #[test]
+    #[serial]
+    fn load_and_roll_name_log_files_using_the_injected_clock() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(1));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        assert_eq!("00000000000000001000", store.current_log_file);
+
+        store.set("foo", "bar").expect("set key");
+
+        let mut actual_files =
+            utils::get_file_names_in_folder(DB_PATH).expect("get files in db folder");
+        actual_files.sort();
+
+        assert_eq!("00000000000000001010-foo", store.index.get("foo").unwrap());
+        assert_eq!("00000000000000001020", store.current_log_file);
+        assert_eq!(vec!["00000000000000001000".to_string()], store.data_files);
+        assert_eq!(
+            vec![
+                "00000000000000001000.cky",
+                "00000000000000001020.log",
+                DEL_FILENAME,
+                INDEX_FILENAME
+            ],
+            actual_files
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn load_after_crash_between_log_roll_and_new_log_creation_recovers_rolled_data() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(2));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        store.set("foo", "bar").expect("set foo");
+        store.set("cow", "moo").expect("set cow");
+
+        // simulate a crash right after the log file was renamed to a data file
+        let log_file = utils::format_timestamp(1030);
+        assert_eq!(log_file, store.current_log_file);
+        fs::remove_file(Path::new(DB_PATH).join(format!("{}.log", log_file)))
+            .expect("remove new log file");
+
+        // the clock restarts from behind the rolled data file and its keys
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(2));
+        store.load().expect("reloads store");
+
+        let data_file = utils::format_timestamp(1000);
+        let log_file = utils::format_timestamp(1021);
+        assert_eq!(log_file, store.current_log_file);
+        assert_eq!(vec![data_file.clone()], store.data_files);
+        assert_eq!("bar", store.get("foo").expect("get foo"));
+        assert_eq!("moo", store.get("cow").expect("get cow"));
+
+        store.set("goat", "baa").expect("set goat");
+        let goat_timestamped_key = format!("{}-goat", utils::format_timestamp(1022));
+        let cow_timestamped_key = format!("{}-cow", utils::format_timestamp(1020));
+        assert_eq!(&goat_timestamped_key, store.index.get("goat").unwrap());
+        assert_eq!(
+            None,
+            store.get_timestamp_range_for_key(&goat_timestamped_key)
+        );
+        assert_eq!(
+            Some((data_file.clone(), log_file.clone())),
+            store.get_timestamp_range_for_key(&cow_timestamped_key)
+        );
+
+        let mut actual_files =
+            utils::get_file_names_in_folder(DB_PATH).expect("get files in db folder");
+        actual_files.sort();
+        assert_eq!(
+            vec![
+                format!("{}.cky", data_file),
+                format!("{}.log", log_file),
+                DEL_FILENAME.to_string(),
+                INDEX_FILENAME.to_string()
+            ],
+            actual_files
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn set_and_get_resolve_ranges_of_timestamps_with_differing_digit_counts() {
+        let (hi, oi) = ("995-hi", "1005-oi");
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        fs::create_dir_all(DB_PATH).expect("create db folder");
+        let db_path = Path::new(DB_PATH);
+        for (file_name, key_values) in [
+            ("990.cky", vec![(hi, "English")]),
+            ("1000.cky", vec![(oi, "Portuguese")]),
+            (INDEX_FILENAME, vec![("hi", hi), ("oi", oi)]),
+        ] {
+            let content = utils::convert_key_values_to_str(key_values);
+            fs::write(db_path.join(file_name), content).expect("write file");
+        }
+        fs::write(db_path.join("1010.log"), "").expect("write log file");
+        fs::write(db_path.join(DEL_FILENAME), "").expect("write del file");
+
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.load().expect("loads store");
+
+        assert_eq!(
+            vec![utils::format_timestamp(990), utils::format_timestamp(1000)],
+            store.data_files
+        );
+        assert_eq!(
+            Some((utils::format_timestamp(990), utils::format_timestamp(1000))),
+            store.get_timestamp_range_for_key(hi)
+        );
+        assert_eq!("English", store.get("hi").expect("get hi"));
+        assert_eq!("Portuguese", store.get("oi").expect("get oi"));
+    }
+
+    #[test]
+    #[serial]
+    fn load_pads_legacy_file_names_with_zeros_and_keeps_legacy_keys_readable() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_max_log_entries(Some(1));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        let db_path = Path::new(DB_PATH);
+        for (legacy_file_name, file_name) in [
+            ("1655375120328185000.cky", DATA_FILES[0]),
+            ("1655375120328186000.cky", DATA_FILES[1]),
+            ("1655375171402014000.log", LOG_FILENAME),
+        ] {
+            fs::rename(db_path.join(file_name), db_path.join(legacy_file_name))
+                .expect("rename to legacy file name");
+        }
+        store.load().expect("loads store");
+
+        let mut actual_files =
+            utils::get_file_names_in_folder(DB_PATH).expect("get files in db folder");
+        actual_files.sort();
+        assert_eq!(
+            vec![
+                DATA_FILES[0],
+                DATA_FILES[1],
+                LOG_FILENAME,
+                DEL_FILENAME,
+                INDEX_FILENAME
+            ],
+            actual_files
+        );
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+
+        // a new key is newer than the legacy keys even though its timestamp has more digits
+        store.set("rat", "2 months").expect("set rat");
+        let rat_timestamped_key = store.index.get("rat").unwrap().clone();
+        assert_eq!(Some('0'), rat_timestamped_key.chars().next());
+        assert_eq!(
+            Some((
+                "01655375171402014000".to_string(),
+                store.current_log_file.clone()
+            )),
+            store.get_timestamp_range_for_key(&rat_timestamped_key)
+        );
+        assert_eq!("2 months", store.get("rat").expect("get rat"));
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+    }
+
+    #[test]
+    #[serial]
+    fn load_reads_folder_written_by_go_and_python_implementations() {
+        // the Go implementation's dummy data is the same as this fixture from the Python one
+        let fixture_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/py_ckydb_db");
+        let expected_values = [
+            ("cow", "500 months"),
+            ("dog", "23 months"),
+            ("goat", "678 months"),
+            ("hen", "567 months"),
+            ("pig", "70 months"),
+            ("fish", "8990 months"),
+        ];
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        fs::create_dir_all(DB_PATH).expect("create db folder");
+        for entry in fs::read_dir(&fixture_path).expect("read fixture folder") {
+            let entry = entry.expect("read fixture file");
+            fs::copy(entry.path(), Path::new(DB_PATH).join(entry.file_name()))
+                .expect("copy fixture file");
+        }
+
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.load().expect("loads store");
+
+        for (key, value) in expected_values {
+            assert_eq!(value, store.get(key).expect(key), "{}", key);
+        }
+        assert!(store.get("foo").is_err());
+        assert!(store.get("bar").is_err());
+        assert!(store.verify().expect("verify store").is_ok());
+
+        store.set("goat", "679 months").expect("update goat");
+        store.set("sheep", "2 months").expect("set sheep");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.load().expect("reloads store");
+        assert_eq!("679 months", store.get("goat").expect("get goat"));
+        assert_eq!("2 months", store.get("sheep").expect("get sheep"));
+        assert_eq!("567 months", store.get("hen").expect("get hen"));
+    }
+
+    #[test]
+    #[serial]
+    fn load_in_memory_creates_no_files_on_disk() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.set_in_memory(true);
+        let empty_map: HashMap<String, String> = Default::default();
+        let mut expected_files = vec![DEL_FILENAME.to_string(), INDEX_FILENAME.to_string()];
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        expected_files.push(format!("{}.log", store.current_log_file));
+        let mut actual_files = store
+            .folder
+            .get_file_names_in_folder(DB_PATH)
+            .expect("get files in memory folder");
+        expected_files.sort();
+        actual_files.sort();
+
+        assert!(!Path::new(DB_PATH).exists());
+        assert_ne!("".to_string(), store.current_log_file);
+        assert_eq!(empty_map, store.index);
+        assert_eq!(empty_map, store.memtable);
+        assert_eq!(EMPTY_LIST, store.data_files);
+        assert_eq!(expected_files, actual_files);
+    }
+
+    #[test]
+    #[serial]
+    fn set_and_get_in_memory_roll_log_file_without_touching_disk() {
+        let records = [
+            ("cow", "500 months"),
+            ("dog", "23 months"),
+            ("hen", "5 months"),
+        ];
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_in_memory(true);
+        store.set_max_log_entries(Some(2));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        for (k, v) in records {
+            store
+                .set(k, v)
+                .unwrap_or_else(|_| panic!("set key: {}, value: {}", k, v));
+        }
+        store.set("cow", "501 months").expect("update key: cow");
+
+        let data_file_path = Path::new(DB_PATH).join(format!("{}.cky", store.data_files[0]));
+        let data_file_content = store
+            .folder
+            .read_to_string(&data_file_path)
+            .expect("read data file");
+
+        assert!(!Path::new(DB_PATH).exists());
+        assert_eq!(1, store.data_files.len());
+        assert!(data_file_content.contains("501 months"));
+        assert_eq!("501 months", store.get("cow").unwrap());
+        assert_eq!("23 months", store.get("dog").unwrap());
+        assert_eq!("5 months", store.get("hen").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn delete_and_vacuum_in_memory_remove_key_values_without_touching_disk() {
+        let (key, value) = ("cow", "500 months");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.set_in_memory(true);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        store
+            .set(key, value)
+            .unwrap_or_else(|_| panic!("set key: {}", key));
+        store.set("dog", "23 months").expect("set key: dog");
+        let timestamped_key = store.index.get(key).unwrap().clone();
+
+        store
+            .delete(key)
+            .unwrap_or_else(|_| panic!("delete {}", key));
+
+        let del_file_content_pre_vacuum =
+            store.folder.read_to_string(&store.del_file_path).unwrap();
+        let log_file_content_pre_vacuum = store
+            .folder
+            .read_to_string(&store.current_log_file_path)
+            .unwrap();
+
+        store.vacuum().expect("vacuum");
+
+        let del_file_content_post_vacuum =
+            store.folder.read_to_string(&store.del_file_path).unwrap();
+        let log_file_content_post_vacuum = store
+            .folder
+            .read_to_string(&store.current_log_file_path)
+            .unwrap();
+
+        assert!(!Path::new(DB_PATH).exists());
+        assert!(del_file_content_pre_vacuum.contains(&timestamped_key));
+        assert!(log_file_content_pre_vacuum.contains(&timestamped_key));
+        assert_eq!("", del_file_content_post_vacuum);
+        assert!(!log_file_content_post_vacuum.contains(&timestamped_key));
+        assert_eq!("23 months", store.get("dog").unwrap());
+        match store.get(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn set_new_key_adds_key_value_to_memtable_and_index_and_log_files() {
+        let (key, value) = ("New key", "foo");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let db_path = Path::new(DB_PATH);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+        let log_file_path = db_path.join(LOG_FILENAME);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+        store
+            .set(key, value)
+            .unwrap_or_else(|_| panic!("set key: {}, value: {}", key, value));
+
+        // expected
+        let timestamped_key = store.index.get(key).unwrap();
+        let expected_index_file_entry = format!(
+            "{}{}{}{}",
+            key, KEY_VALUE_SEPARATOR, timestamped_key, TOKEN_SEPARATOR
+        );
+        let expected_log_file_entry = format!(
+            "{}{}{}{}",
+            timestamped_key, KEY_VALUE_SEPARATOR, value, TOKEN_SEPARATOR
+        );
+
+        // actual
+        let value_in_memtable = store.memtable.get(timestamped_key).unwrap();
+        let index_file_content = fs::read_to_string(index_file_path).expect("read index file");
+        let log_file_content = fs::read_to_string(log_file_path).expect("read log file");
+
+        assert_eq!(value, value_in_memtable);
+        assert!(index_file_content.contains(&expected_index_file_entry));
+        assert!(log_file_content.contains(&expected_log_file_entry));
+    }
+
+    #[test]
+    #[serial]
+    fn insert_new_with_existing_key_returns_already_exists_error_while_set_overwrites() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        store.insert_new("id-1", "foo").expect("insert new id-1");
+        match store.insert_new("id-1", "bar") {
+            Ok(_) => panic!("second insert_new of id-1 should fail"),
+            Err(err) => {
+                assert_eq!(io::ErrorKind::AlreadyExists, err.kind());
+                assert!(err.to_string().contains("already exists"));
+            }
+        }
+        assert_eq!("foo", store.get("id-1").unwrap());
+        assert!(store.insert_new("cow", "1 month").is_err());
+        assert_eq!("500 months", store.get("cow").unwrap());
+
+        store.set("id-1", "bar").expect("set id-1");
+        assert_eq!("bar", store.get("id-1").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn set_new_keys_at_the_same_time_creates_unique_ordered_timestamped_keys() {
+        let keys: Vec<String> = (0..100).map(|i| format!("key-{}", i)).collect();
+        let frozen_timestamp = 1655375171402014000;
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB * 100.0);
+        store.set_clock(Box::new(MockClock::new(frozen_timestamp, 0)));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        for key in &keys {
+            store
+                .set(key, "foo")
+                .unwrap_or_else(|_| panic!("set key: {}", key));
+        }
+
+        let timestamps: Vec<u128> = keys
+            .iter()
+            .map(|key| {
+                let timestamped_key = store.index.get(key).unwrap();
+                let (timestamp, _) = timestamped_key.split_once("-").unwrap();
+                timestamp.parse().unwrap()
+            })
+            .collect();
+        let mut timestamped_keys: Vec<&String> = store.index.values().collect();
+        timestamped_keys.sort();
+        let keys_in_timestamp_order: Vec<String> = timestamped_keys
+            .into_iter()
+            .map(|k| k.split_once("-").unwrap().1.to_string())
+            .collect();
+
+        let expected_timestamps: Vec<u128> = (1..=keys.len() as u128)
+            .map(|i| frozen_timestamp + i)
+            .collect();
+
+        assert_eq!(expected_timestamps, timestamps);
+        assert_eq!(keys, keys_in_timestamp_order);
+        assert_eq!(keys.len(), store.memtable.len());
+    }
+
+    #[test]
+    #[serial]
+    fn set_empty_key_returns_invalid_key_error() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        match store.set("", "v") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("invalid key")),
+        }
+
+        match store.delete("") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("invalid key")),
+        }
+
+        let index_file_content = fs::read_to_string(index_file_path).expect("read index file");
+        let empty_map: HashMap<String, String> = Default::default();
+
+        assert_eq!("", index_file_content);
+        assert_eq!(empty_map, store.index);
+        assert_eq!(empty_map, store.memtable);
+    }
+
+    #[test]
+    #[serial]
+    fn set_value_bigger_than_max_value_size_returns_value_too_large_error() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.set_max_value_size_bytes(Some(5));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+        let index_file_content_before = fs::read_to_string(&index_file_path).unwrap();
+        let log_file_content_before = fs::read_to_string(&store.current_log_file_path).unwrap();
+        let memtable_before = store.memtable.clone();
+
+        for (k, v) in [("cow", "123456"), ("sheep", "a very long value")] {
+            match store.set(k, v) {
+                Ok(_) => panic!("error was expected"),
+                Err(err) => assert!(err.to_string().contains("value too large")),
+            }
+        }
+
+        assert_eq!(
+            index_file_content_before,
+            fs::read_to_string(&index_file_path).unwrap()
+        );
+        assert_eq!(
+            log_file_content_before,
+            fs::read_to_string(&store.current_log_file_path).unwrap()
+        );
+        assert_eq!(memtable_before, store.memtable);
+        assert_eq!("500 months", store.get("cow").unwrap());
+
+        store.set("sheep", "12345").expect("set value of max size");
+
+        assert_eq!("12345", store.get("sheep").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn set_same_recent_key_updates_value_in_memtable_and_log_file() {
+        let (key, value, new_value) = ("New key", "foo", "hello-world");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let db_path = Path::new(DB_PATH);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+        let log_file_path = db_path.join(LOG_FILENAME);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+        store
+            .set(key, value)
+            .unwrap_or_else(|_| panic!("set key: {}, value: {}", key, value));
+        store
+            .set(key, new_value)
+            .unwrap_or_else(|_| panic!("set key: {}, value: {}", key, new_value));
+
+        // expected
+        let timestamped_key = store.index.get(key).unwrap();
+        let expected_index_file_entry = format!(
+            "{}{}{}{}",
+            key, KEY_VALUE_SEPARATOR, timestamped_key, TOKEN_SEPARATOR
+        );
+        let expected_log_file_entry = format!(
+            "{}{}{}{}",
+            timestamped_key, KEY_VALUE_SEPARATOR, new_value, TOKEN_SEPARATOR
+        );
+
+        // actual
+        let value_in_memtable = store.memtable.get(timestamped_key).unwrap();
+        let index_file_content = fs::read_to_string(index_file_path).expect("read index file");
+        let log_file_content = fs::read_to_string(log_file_path).expect("read log file");
+
+        assert_eq!(new_value, value_in_memtable);
+        assert!(index_file_content.contains(&expected_index_file_entry));
+        assert!(log_file_content.contains(&expected_log_file_entry));
+    }
+
+    #[test]
+    #[serial]
+    fn set_old_key_updates_value_in_cache_and_in_cky_file() {
+        let (key, value) = ("cow", "foo-again");
+        let db_path = Path::new(DB_PATH);
+        let data_file_path = db_path.join(DATA_FILES[0]);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+        store
+            .set(key, value)
+            .unwrap_or_else(|_| panic!("set key: {}, value: {}", key, value));
+
+        // expected
+        let timestamped_key = store.index.get(key).unwrap();
+        let expected_data_file_entry =
+            format!("{}{}{}", timestamped_key, KEY_VALUE_SEPARATOR, value);
+
+        // actual
+        let value_in_cache = store.cache.get(timestamped_key).unwrap();
+        let data_file_content = fs::read_to_string(data_file_path).expect("read data file");
+
+        assert_eq!(value, value_in_cache);
+        assert!(data_file_content.contains(&expected_data_file_entry));
+    }
+
+    #[test]
+    #[serial]
+    fn set_rolls_log_file_when_memtable_reaches_max_log_entries() {
+        let max_log_entries = 3;
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_max_log_entries(Some(max_log_entries));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        let first_log_file = store.current_log_file.clone();
+
+        for i in 0..(max_log_entries - 1) {
+            store.set(&i.to_string(), "a").expect("set key");
+        }
+
+        assert_eq!(first_log_file, store.current_log_file);
+        assert_eq!(EMPTY_LIST, store.data_files);
+        assert_eq!(max_log_entries - 1, store.memtable.len());
+
+        store.set("last", "a").expect("set key");
+
+        let cky_file_contents = utils::read_files_with_extension(DB_PATH, "cky").unwrap();
+        assert_ne!(first_log_file, store.current_log_file);
+        assert_eq!(vec![first_log_file], store.data_files);
+        assert_eq!(0, store.memtable.len());
+        assert_eq!(1, cky_file_contents.len());
+        assert_eq!(
+            max_log_entries,
+            utils::extract_tokens_from_str(&cky_file_contents[0]).len()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn checkpoint_rolls_log_file_into_data_file_and_empties_memtable() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        store.set("cow", "500 months").expect("set cow");
+        store.set("dog", "23 months").expect("set dog");
+        let log_file = store.current_log_file.clone();
+
+        store.checkpoint().expect("checkpoints store");
+
+        assert!(store.memtable.is_empty());
+        assert_eq!(vec![log_file.clone()], store.data_files);
+        assert_ne!(log_file, store.current_log_file);
+        let data_file_content =
+            fs::read_to_string(Path::new(DB_PATH).join(format!("{}.cky", log_file)))
+                .expect("read data file");
+        let data = utils::extract_key_values_from_str(&data_file_content).expect("parse data");
+        assert_eq!(
+            Some(&"500 months".to_string()),
+            data.get(&store.index["cow"])
+        );
+        assert_eq!(
+            Some(&"23 months".to_string()),
+            data.get(&store.index["dog"])
+        );
+        let log_file_content = fs::read_to_string(&store.current_log_file_path).expect("read log");
+        assert_eq!("", log_file_content);
+
+        // nothing is rolled if the memtable is empty
+        store.checkpoint().expect("checkpoints empty memtable");
+        assert_eq!(vec![log_file], store.data_files);
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+        assert_eq!("23 months", store.get("dog").expect("get dog"));
+    }
+
+    #[test]
+    #[serial]
+    fn set_runs_roll_hook_with_new_data_file_after_log_file_is_rolled() {
+        let rolled_files = Arc::new(Mutex::new(vec![]));
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(2));
+        let hook_rolled_files = Arc::clone(&rolled_files);
+        store.set_roll_hook(Some(RollHook::new(move |data_file| {
+            hook_rolled_files
+                .lock()
+                .unwrap()
+                .push(data_file.to_string());
+            Ok(())
+        })));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        store.set("cow", "500 months").expect("set cow");
+        assert!(rolled_files.lock().unwrap().is_empty());
+        store.set("dog", "23 months").expect("set dog");
+
+        let data_file = format!("{}.cky", utils::format_timestamp(1000));
+        assert_eq!(vec![data_file.clone()], *rolled_files.lock().unwrap());
+        assert!(Path::new(DB_PATH).join(&data_file).exists());
+
+        // a failing hook is reported distinctly, but the key-value pair is kept
+        store.set_roll_hook(Some(RollHook::new(|_| {
+            Err(io::Error::other("upload failed"))
+        })));
+        store.set("goat", "678 months").expect("set goat");
+        let err = store
+            .set("hen", "567 months")
+            .expect_err("failing roll hook");
+        assert!(err.to_string().contains("roll hook failed"));
+        assert!(err.to_string().contains("upload failed"));
+        assert_eq!("567 months", store.get("hen").expect("get hen"));
+        assert_eq!(2, store.data_files.len());
+    }
+
+    #[test]
+    #[serial]
+    fn load_and_reload_run_load_progress_hook_for_every_file_in_folder() {
+        let progress = Arc::new(Mutex::new(vec![]));
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.set_vacuum_on_load(false);
+        let hook_progress = Arc::clone(&progress);
+        store.set_load_progress_hook(Some(LoadProgressHook::new(
+            move |files_processed, total_files| {
+                hook_progress
+                    .lock()
+                    .unwrap()
+                    .push((files_processed, total_files));
+            },
+        )));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        // the index, del and log files, and the two data files
+        let expected: Vec<(usize, usize)> = (1..=5).map(|i| (i, 5)).collect();
+        assert_eq!(expected, *progress.lock().unwrap());
+
+        progress.lock().unwrap().clear();
+        store.reload().expect("reloads store");
+        assert_eq!(expected, *progress.lock().unwrap());
+
+        store.set_load_progress_hook(None);
+        store.reload().expect("reloads store");
+        assert_eq!(expected, *progress.lock().unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn set_with_any_fsync_policy_persists_values_that_can_be_reloaded() {
+        for fsync in [
+            FsyncPolicy::Never,
+            FsyncPolicy::OnEverySet,
+            FsyncPolicy::OnRoll,
+        ] {
+            let mut store = Store::new(DB_PATH, 1024.0);
+            store.set_fsync(fsync);
+            store.set_max_log_entries(Some(2));
+
+            utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+            store.load().expect("loads store");
+
+            for i in 0..5 {
+                store.set(&i.to_string(), "a").expect("set key");
+            }
+            store.set("0", "b").expect("update old key");
+
+            let mut reloaded_store = Store::new(DB_PATH, 1024.0);
+            reloaded_store.load().expect("loads store");
+
+            assert_eq!("b", reloaded_store.get("0").unwrap(), "{:?}", fsync);
+            for i in 1..5 {
+                assert_eq!(
+                    "a",
+                    reloaded_store.get(&i.to_string()).unwrap(),
+                    "{:?}",
+                    fsync
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn set_with_flush_every_n_buffers_sets_till_nth_set_or_flush() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_flush_every_n(Some(3));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        store.set("hi", "English").expect("set hi");
+        store.set("oi", "Portuguese").expect("set oi");
+        let log_file_content = fs::read_to_string(&store.current_log_file_path).unwrap();
+        assert_eq!("", log_file_content);
+
+        store.set("hola", "Spanish").expect("set hola");
+        let log_file_content = fs::read_to_string(&store.current_log_file_path).unwrap();
+        assert_eq!(3, utils::extract_tokens_from_str(&log_file_content).len());
+
+        store.set("salut", "French").expect("set salut");
+        store.flush().expect("flush");
+        let log_file_content = fs::read_to_string(&store.current_log_file_path).unwrap();
+        assert_eq!(4, utils::extract_tokens_from_str(&log_file_content).len());
+    }
+
+    #[test]
+    #[serial]
+    fn set_many_from_iter_writes_all_pairs_and_flushes_log_file_once() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_max_log_entries(Some(2));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        let first_log_file = store.current_log_file.clone();
+
+        let count = store
+            .set_many_from_iter(&mut (0..5).map(|i| (format!("key{}", i), i.to_string())))
+            .expect("set many");
+
+        // the log file is rolled once with all the pairs, not after every two of them
+        let cky_file_contents = utils::read_files_with_extension(DB_PATH, "cky").unwrap();
+        assert_eq!(5, count);
+        assert_eq!(vec![first_log_file], store.data_files);
+        assert_eq!(1, cky_file_contents.len());
+        assert_eq!(
+            5,
+            utils::extract_tokens_from_str(&cky_file_contents[0]).len()
+        );
+
+        let mut reloaded_store = Store::new(DB_PATH, 1024.0);
+        reloaded_store.load().expect("loads store");
+        for i in 0..5 {
+            assert_eq!(
+                i.to_string(),
+                reloaded_store.get(&format!("key{}", i)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn set_many_from_iter_with_empty_key_writes_pairs_before_it() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        let pairs = vec![
+            ("hi".to_string(), "English".to_string()),
+            ("".to_string(), "empty".to_string()),
+            ("oi".to_string(), "Portuguese".to_string()),
+        ];
+
+        match store.set_many_from_iter(&mut pairs.into_iter()) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("invalid key")),
+        }
+
+        let mut reloaded_store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        reloaded_store.load().expect("loads store");
+        assert_eq!("English", reloaded_store.get("hi").unwrap());
+        assert!(reloaded_store.get("oi").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn set_old_keys_with_growing_values_splits_data_file_when_too_big() {
+        let max_file_size_kb = 1.0;
+        let number_of_keys = 10;
+        let mut store = Store::new(DB_PATH, max_file_size_kb);
+        store.set_split_data_files(true);
+        store.set_max_log_entries(Some(number_of_keys));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        for i in 0..number_of_keys {
+            store.set(&format!("key{}", i), "a").expect("set key");
+        }
+
+        assert_eq!(1, store.data_files.len());
+
+        let big_value = "x".repeat(200);
+        for i in 0..number_of_keys {
+            store
+                .set(&format!("key{}", i), &big_value)
+                .expect("update key");
+        }
+
+        let cky_file_contents = utils::read_files_with_extension(DB_PATH, "cky").unwrap();
+        assert!(store.data_files.len() > 1);
+        assert_eq!(store.data_files.len(), cky_file_contents.len());
+        for content in &cky_file_contents {
+            assert!(utils::extract_tokens_from_str(content).len() < number_of_keys);
+        }
+
+        let mut reloaded_store = Store::new(DB_PATH, max_file_size_kb);
+        reloaded_store.load().expect("loads store");
+        for i in 0..number_of_keys {
+            assert_eq!(big_value, reloaded_store.get(&format!("key{}", i)).unwrap());
+        }
+        assert!(reloaded_store.verify().unwrap().is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn get_new_key_gets_value_from_memtable() {
+        let (key, expected_value) = ("fish", "8990 months");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        // remove the database files to show data is got straight from memory
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+
+        let actual_value = store.get(key).unwrap();
+        assert_eq!(actual_value, expected_value);
+    }
+
+    #[test]
+    #[serial]
+    fn get_value_for_deleted_old_key_returns_error_before_vacuum() {
+        let timestamped_keys = ["1655375120328185000-cow", "1655375171402014000-bar"];
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.set_vacuum_on_load(false);
+        store.set_resilient_reads(true);
+        store.load().expect("loads store");
+        store.delete("cow").expect("delete cow");
+
+        // the values are still on disk until the next vacuum
+        let data_files_content = DATA_FILES
+            .iter()
+            .map(|f| fs::read_to_string(Path::new(DB_PATH).join(f)).expect("read data file"))
+            .collect::<String>();
+        for timestamped_key in timestamped_keys {
+            assert!(data_files_content.contains(timestamped_key));
+        }
+
+        for _ in 0..2 {
+            for timestamped_key in timestamped_keys {
+                assert!(store.get_value_for_key(timestamped_key).is_err());
+                assert!(store.find_value_in_all_data_files(timestamped_key).is_err());
+                assert_eq!(None, store.cache.get(timestamped_key));
+            }
+            assert_eq!("23 months", store.get("dog").unwrap());
+            store.cache = Cache::new_empty();
+        }
+        assert!(store.snapshot().get("cow").is_err());
+        assert!(!store.contains_value("500 months").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_updates_cache_from_disk_and_gets_value_from_cache() {
+        let (key, expected_value) = ("cow", "500 months");
+        let expected_initial_cache = Cache::new_empty();
+        let expected_final_cache = Cache::new(
+            HashMap::from([
+                (
+                    String::from("1655375120328185000-cow"),
+                    String::from("500 months"),
+                ),
+                (
+                    String::from("1655375120328185100-dog"),
+                    String::from("23 months"),
+                ),
+            ]),
+            DATA_FILES[0].trim_end_matches(".cky"),
+            DATA_FILES[1].trim_end_matches(".cky"),
+        );
+
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        let initial_cache = store.cache.clone();
+        let value = store.get(key).unwrap();
+        let final_cache = store.cache.clone();
+
+        assert_eq!(expected_value, value);
+        assert_eq!(expected_initial_cache, initial_cache);
+        assert_eq!(expected_final_cache, final_cache);
+    }
+
+    #[test]
+    #[serial]
+    fn preload_range_loads_data_file_into_cache_for_later_reads() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        store
+            .preload_range("1655375120328185000", "1655375120328185999")
+            .expect("preload range");
+        fs::remove_file(Path::new(DB_PATH).join(DATA_FILES[0])).expect("remove data file");
+
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("23 months", store.get("dog").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn preload_range_does_nothing_for_empty_range_or_log_file_range() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        store
+            .preload_range("1655375120328185999", "1655375120328185000")
+            .expect("preload empty range");
+        store
+            .preload_range(&store.current_log_file.clone(), "9")
+            .expect("preload log file range");
+
+        assert_eq!(Cache::new_empty(), store.cache);
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_does_not_keep_data_file_bigger_than_cache_memory_budget_in_cache() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.set_cache_memory_budget_kb(Some(40.0 / 1024.0));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!(Cache::new_empty(), store.cache);
+
+        store.set("dog", "24 months").expect("update dog");
+        assert_eq!(Cache::new_empty(), store.cache);
+        assert_eq!("24 months", store.get("dog").unwrap());
+
+        store.set_cache_memory_budget_kb(Some(1.0));
+        store.get("cow").unwrap();
+        assert_eq!("01655375120328185000", store.cache.start);
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_keys_keeps_recently_used_data_files_in_cache_within_cache_memory_budget() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_max_log_entries(Some(1));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        // each key is rolled into a data file of its own, of about 0.12 kB
+        let value = "v".repeat(100);
+        let keys = ["cow", "dog", "goat", "hen"];
+        for key in keys {
+            store.set(key, &value).expect("set key");
+        }
+        assert_eq!(4, store.data_files.len());
+        // enough for the data files of two keys, but not three
+        store.set_cache_memory_budget_kb(Some(0.25));
+
+        for key in ["cow", "dog", "goat"] {
+            assert_eq!(value, store.get(key).unwrap());
+            assert!(!store.last_read_was_cached(), "{}", key);
+        }
+
+        // the data file of cow, the least recently used, was evicted
+        assert_eq!(1, store.cached_ranges.len());
+        store.get("dog").unwrap();
+        assert!(store.last_read_was_cached());
+        store.get("goat").unwrap();
+        assert!(store.last_read_was_cached());
+        store.get("cow").unwrap();
+        assert!(!store.last_read_was_cached());
+
+        // without a budget, only the current data file is kept in the cache
+        store.set_cache_memory_budget_kb(None);
+        store.clear_cache();
+        store.get("dog").unwrap();
+        store.get("goat").unwrap();
+        store.get("dog").unwrap();
+        assert!(!store.last_read_was_cached());
+        assert!(store.cached_ranges.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn last_read_was_cached_reports_whether_get_loaded_data_file() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        store.get("cow").unwrap();
+        assert!(!store.last_read_was_cached());
+
+        store.get("cow").unwrap();
+        assert!(store.last_read_was_cached());
+
+        store.get("goat").unwrap();
+        assert!(store.last_read_was_cached());
+    }
+
+    #[test]
+    #[serial]
+    fn delete_with_unwritable_index_file_puts_store_in_read_only_degraded_mode() {
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        fs::remove_file(&index_file_path).expect("remove index file");
+        fs::create_dir(&index_file_path).expect("create folder in place of index file");
+
+        match store.delete("cow") {
+            Ok(_) => panic!("delete should fail when the index file is unwritable"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        assert!(store.is_degraded());
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("678 months", store.get("goat").unwrap());
+
+        fs::remove_dir(&index_file_path).expect("remove folder in place of index file");
+    }
+
+    #[test]
+    #[serial]
+    fn set_with_unwritable_index_file_puts_store_in_read_only_degraded_mode() {
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+        assert!(!store.is_degraded());
+
+        // a folder in place of the index file makes it unwritable even for privileged users,
+        // who would ignore read-only permissions
+        let index_file_content = fs::read_to_string(&index_file_path).expect("read index file");
+        fs::remove_file(&index_file_path).expect("remove index file");
+        fs::create_dir(&index_file_path).expect("create folder in place of index file");
+
+        match store.set("sheep", "2 months") {
+            Ok(_) => panic!("set should fail when the index file is unwritable"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        assert!(store.is_degraded());
+        assert!(!store.index.contains_key("sheep"));
+
+        for (key, value) in [("goat", "1 month"), ("lamb", "1 week")] {
+            match store.set(key, value) {
+                Ok(_) => panic!("set of {} should be rejected in degraded mode", key),
+                Err(err) => assert!(err.to_string().contains("degraded")),
+            }
+        }
+        match store.delete("goat") {
+            Ok(_) => panic!("delete should be rejected in degraded mode"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        assert!(store.vacuum().is_err());
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("678 months", store.get("goat").unwrap());
+        assert!(store.get("sheep").is_err());
+
+        fs::remove_dir(&index_file_path).expect("remove folder in place of index file");
+        fs::write(&index_file_path, &index_file_content).expect("restore index file");
+        store.load().expect("reloads store");
+        assert!(!store.is_degraded());
+        store.set("sheep", "2 months").expect("set sheep");
+        assert_eq!("2 months", store.get("sheep").unwrap());
+
+        // setting many pairs at once also puts the store in degraded mode
+        fs::write(&index_file_path, &index_file_content).expect("restore index file");
+        store.load().expect("reloads store");
+        fs::remove_file(&index_file_path).expect("remove index file");
+        fs::create_dir(&index_file_path).expect("create folder in place of index file");
+
+        let mut pairs = vec![
+            ("lamb".to_string(), "1 week".to_string()),
+            ("goat".to_string(), "1 month".to_string()),
+        ]
+        .into_iter();
+        match store.set_many_from_iter(&mut pairs) {
+            Ok(_) => panic!("set many should fail when the index file is unwritable"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        assert!(store.is_degraded());
+        assert!(!store.index.contains_key("lamb"));
+        assert!(store.get("lamb").is_err());
+        assert_eq!("500 months", store.get("cow").unwrap());
+
+        fs::remove_dir(&index_file_path).expect("remove folder in place of index file");
+        fs::write(&index_file_path, &index_file_content).expect("restore index file");
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_from_missing_data_file_scans_other_data_files_only_if_resilient() {
+        let db_path = Path::new(DB_PATH);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        fs::rename(
+            db_path.join(DATA_FILES[0]),
+            db_path.join("01655375120328185050.cky"),
+        )
+        .expect("rename data file");
+
+        let err = store.try_get("cow").expect_err("strict read of cow");
+        assert!(err.to_string().contains("corrupted"));
+
+        store.set_resilient_reads(true);
+        assert_eq!(
+            Some("500 months".to_string()),
+            store.try_get("cow").unwrap()
         );
-        let expected_memtable = HashMap::from(
-            [
-                ("1655404770518678-goat", "678 months"),
-                ("1655404670510698-hen", "567 months"),
-                ("1655404770534578-pig", "70 months"),
-                ("1655403775538278-fish", "8990 months"),
-            ]
-            .map(|(k, v)| (k.to_string(), v.to_string())),
+        assert_eq!(Some("23 months".to_string()), store.try_get("dog").unwrap());
+        assert_eq!(Some("70 months".to_string()), store.try_get("pig").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn get_old_key_again_gets_value_straight_from_cache() {
+        let (key, expected_value) = ("cow", "500 months");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        store.load().expect("loads store");
+
+        let _ = store.get(key).unwrap();
+
+        // remove the database files to show data is got straight from memory on next get
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+
+        let value = store.get(key).unwrap();
+
+        assert_eq!(expected_value, value);
+    }
+
+    #[test]
+    #[serial]
+    fn get_map_gets_values_of_found_keys_from_memtable_and_cache() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let expected: HashMap<String, String> = HashMap::from([
+            ("cow".to_string(), "500 months".to_string()),
+            ("goat".to_string(), "678 months".to_string()),
+            ("dog".to_string(), "23 months".to_string()),
+        ]);
+
+        assert_eq!(
+            expected,
+            store.get_map(&["cow", "goat", "bar", "dog", "non-existent"])
         );
-        let expected_data_files = DATA_FILES
-            .map(|filename| filename.trim_end_matches(".cky").to_string())
-            .to_vec();
-        let expected_current_log_file = LOG_FILENAME.trim_end_matches(".log").to_string();
+        assert_eq!("01655375120328185000", store.cache.start);
+    }
+
+    #[test]
+    #[serial]
+    fn get_map_skips_keys_whose_values_cannot_be_read() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        // the data file holding cow and dog goes missing
+        fs::remove_file(Path::new(DB_PATH).join(DATA_FILES[0])).expect("remove data file");
+
+        let expected: HashMap<String, String> =
+            HashMap::from([("goat".to_string(), "678 months".to_string())]);
+
+        assert_eq!(
+            expected,
+            store.get_map(&["cow", "goat", "bar", "dog", "non-existent"])
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn multi_get_returns_results_in_order_of_keys() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let keys = ["goat", "bar", "dog", "non-existent", "cow", "goat"];
+        let results: Vec<Option<String>> = store
+            .multi_get(&keys)
+            .into_iter()
+            .map(|result| result.ok())
+            .collect();
+
+        let expected = vec![
+            Some("678 months".to_string()),
+            None,
+            Some("23 months".to_string()),
+            None,
+            Some("500 months".to_string()),
+            Some("678 months".to_string()),
+        ];
+        assert_eq!(expected, results);
+        assert_eq!("01655375120328185000", store.cache.start);
+        match &store.multi_get(&["bar"])[0] {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "encryption")]
+    fn set_with_encryption_key_saves_values_encrypted_on_disk() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_encryption_key(Some([7; 32]));
+        store.set_track_last_modified(true);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        store.set("cow", "500 months").expect("set cow");
+        store.set("dog", "23 months").expect("set dog");
+        store.roll_log_file().expect("roll log file");
+        store.set("goat", "678 months").expect("set goat");
+
+        let files_content: String = fs::read_dir(DB_PATH)
+            .expect("read db folder")
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).expect("read file"))
+            .collect();
+        assert!(files_content.contains("cow"));
+        for value in ["500 months", "23 months", "678 months"] {
+            assert!(!files_content.contains(value), "{} is in the clear", value);
+        }
+
+        store.load().expect("reloads store");
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("678 months", store.get("goat").unwrap());
+        assert_eq!("23 months", store.snapshot().get("dog").unwrap());
+        assert!(store.last_modified("goat").is_ok());
+
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_encryption_key(Some([8; 32]));
+        store.set_track_last_modified(true);
+        match store.load() {
+            Ok(_) => panic!("loading with the wrong key should fail"),
+            Err(err) => assert!(err.to_string().contains("wrong encryption key")),
+        }
+        match store.try_get("cow") {
+            Ok(_) => panic!("decryption with the wrong key should fail"),
+            Err(err) => assert!(err.to_string().contains("corrupted data")),
+        }
+        assert!(store.snapshot().get("goat").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn last_modified_returns_time_of_latest_set_if_tracked() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(2));
+        store.set_track_last_modified(true);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        store.set("cow", "500 months").expect("set cow");
+        let first_modified = store.last_modified("cow").expect("last modified cow");
+        store.set("cow", "501 months").expect("update cow");
+        let second_modified = store.last_modified("cow").expect("last modified cow");
+        // "cow" is now in a data file
+        store.set("dog", "23 months").expect("set dog");
+        store.set("cow", "502 months").expect("update cow");
+        let third_modified = store.last_modified("cow").expect("last modified cow");
+
+        assert!(first_modified < second_modified);
+        assert!(second_modified < third_modified);
+        assert_eq!("502 months", store.get("cow").expect("get cow"));
+        assert_eq!("23 months", store.get("dog").expect("get dog"));
+        assert_eq!(
+            vec![("cow".to_string(), "502 months".to_string())],
+            store.filter(&|key, _| key == "cow").expect("filter cow")
+        );
+        assert_eq!(
+            "502 months",
+            store.snapshot().get("cow").expect("snapshot cow")
+        );
+
+        let err = store
+            .last_modified("non-existent")
+            .expect_err("non-existent");
+        assert!(err.to_string().contains("not found"));
+
+        store.set_track_last_modified(false);
+        store.set("dog", "24 months").expect("update dog");
+        let err = store.last_modified("dog").expect_err("untracked dog");
+        assert!(err.to_string().contains("not tracked"));
+    }
+
+    #[test]
+    #[serial]
+    fn try_get_returns_none_for_missing_key_and_error_for_corrupted_data() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        assert_eq!(
+            Some("500 months".to_string()),
+            store.try_get("cow").unwrap()
+        );
+        assert_eq!(Some("70 months".to_string()), store.try_get("pig").unwrap());
+        assert_eq!(None, store.try_get("non-existent").unwrap());
+        assert_eq!(None, store.try_get("").unwrap());
+
+        // simulate corruption by losing the value of a key in the index
+        store.memtable.remove("1655404770534578000-pig");
+        match store.try_get("pig") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("corrupted data")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn get_non_existent_key_returns_not_found_error() {
+        let key = "non-existent";
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        match store.get(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_key_removes_key_from_index_and_adds_it_to_del_file() {
+        let key = "pig";
+        let expected_index = HashMap::from([
+            (String::from("cow"), String::from("1655375120328185000-cow")),
+            (String::from("dog"), String::from("1655375120328185100-dog")),
+            (
+                String::from("goat"),
+                String::from("1655404770518678000-goat"),
+            ),
+            (String::from("hen"), String::from("1655404670510698000-hen")),
+            (
+                String::from("fish"),
+                String::from("1655403775538278000-fish"),
+            ),
+        ]);
+        let expected_keys_marked_for_delete = vec!["1655404770534578000-pig"];
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let db_path = Path::new(DB_PATH);
+        let index_file_path = db_path.join(INDEX_FILENAME);
+        let del_file_path = db_path.join(DEL_FILENAME);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store
+            .delete(key)
+            .unwrap_or_else(|_| panic!("delete {}", key));
+
+        let idx_file_content = fs::read_to_string(index_file_path).expect("read index file");
+        let del_file_content = fs::read_to_string(del_file_path).expect("read del file");
+        let map_from_idx_file = utils::extract_key_values_from_str(&idx_file_content)
+            .expect("extract key values from index");
+        let list_from_del_file = utils::extract_tokens_from_str(&del_file_content);
+
+        match store.get(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+
+        assert_eq!(expected_index, map_from_idx_file);
+        assert_eq!(expected_keys_marked_for_delete, list_from_del_file);
+        assert_eq!(expected_index, store.index);
+    }
+
+    #[test]
+    #[serial]
+    fn delete_key_removes_value_from_memory_but_leaves_it_on_disk_till_vacuum() {
+        let (new_key, old_key) = ("fish", "cow");
+        let db_path = Path::new(DB_PATH);
+        let log_file_path = db_path.join(LOG_FILENAME);
+        let data_file_path = db_path.join(DATA_FILES[0]);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        // load the cache containing the old key
+        store
+            .get(old_key)
+            .unwrap_or_else(|_| panic!("get {}", old_key));
+
+        let new_timestamped_key = store.index.get(new_key).unwrap().clone();
+        let old_timestamped_key = store.index.get(old_key).unwrap().clone();
+
+        store
+            .delete(new_key)
+            .unwrap_or_else(|_| panic!("delete {}", new_key));
+        store
+            .delete(old_key)
+            .unwrap_or_else(|_| panic!("delete {}", old_key));
+
+        let log_file_content = fs::read_to_string(&log_file_path).expect("read log file");
+        let data_file_content = fs::read_to_string(&data_file_path).expect("read data file");
+
+        assert_eq!(None, store.memtable.get(&new_timestamped_key));
+        assert_eq!(None, store.cache.get(&old_timestamped_key));
+        assert!(log_file_content.contains(&new_timestamped_key));
+        assert!(data_file_content.contains(&old_timestamped_key));
+
+        // values of deleted keys are kept on disk when their files are rewritten
+        store.set("goat", "1 month").expect("set goat");
+        store.set("dog", "2 months").expect("set dog");
+
+        let log_file_content = fs::read_to_string(&log_file_path).expect("read log file");
+        let data_file_content = fs::read_to_string(&data_file_path).expect("read data file");
+
+        assert_eq!(None, store.memtable.get(&new_timestamped_key));
+        assert_eq!(None, store.cache.get(&old_timestamped_key));
+        assert!(log_file_content.contains(&new_timestamped_key));
+        assert!(data_file_content.contains(&old_timestamped_key));
+
+        store.vacuum().expect("vacuum");
+
+        let log_file_content = fs::read_to_string(&log_file_path).expect("read log file");
+        let data_file_content = fs::read_to_string(&data_file_path).expect("read data file");
+
+        assert!(!log_file_content.contains(&new_timestamped_key));
+        assert!(!data_file_content.contains(&old_timestamped_key));
+    }
+
+    #[test]
+    #[serial]
+    fn rename_old_key_moves_value_to_memtable_and_marks_old_entry_for_deletion() {
+        let (key, new_key, value) = ("cow", "calf", "500 months");
+        let old_timestamped_key = "1655375120328185000-cow";
         let db_path = Path::new(DB_PATH);
-        let log_file_path = db_path.join(LOG_FILENAME);
-        let index_file_path = db_path.join(INDEX_FILENAME);
+        let data_file_path = db_path.join(DATA_FILES[0]);
         let del_file_path = db_path.join(DEL_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
+        store
+            .rename(key, new_key)
+            .unwrap_or_else(|_| panic!("rename {} to {}", key, new_key));
 
-        assert_eq!(expected_cache, store.cache);
-        assert_eq!(expected_memtable, store.memtable);
-        assert_eq!(expected_index, store.index);
-        assert_eq!(expected_data_files, store.data_files);
-        assert_eq!(expected_current_log_file, store.current_log_file);
-        assert_eq!(log_file_path, store.current_log_file_path);
-        assert_eq!(index_file_path, store.index_file_path);
-        assert_eq!(del_file_path, store.del_file_path);
+        let new_timestamped_key = store.index.get(new_key).unwrap().clone();
+        let del_file_content = fs::read_to_string(&del_file_path).expect("read del file");
+
+        assert!(new_timestamped_key > store.current_log_file);
+        assert_eq!(value, store.memtable.get(&new_timestamped_key).unwrap());
+        assert_eq!(None, store.index.get(key));
+        assert_eq!(value, store.get(new_key).unwrap());
+        assert!(del_file_content.contains(old_timestamped_key));
+
+        store.vacuum().expect("vacuum");
+
+        let data_file_content = fs::read_to_string(&data_file_path).expect("read data file");
+        assert!(!data_file_content.contains(old_timestamped_key));
+        match store.get(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
     }
 
     #[test]
     #[serial]
-    fn load_creates_db_folder_with_del_and_index_files_if_not_exist() {
-        let expected_cache = Cache::new_empty();
-        let mut expected_files = [DEL_FILENAME, INDEX_FILENAME].map(String::from).to_vec();
+    fn rename_that_fails_to_save_value_keeps_both_keys_unchanged() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.set_max_value_size_bytes(Some("23 months".len()));
+
+        match store.rename("cow", "dog") {
+            Ok(_) => panic!("rename of too large a value should fail"),
+            Err(err) => assert_eq!(io::ErrorKind::InvalidInput, err.kind()),
+        }
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("23 months", store.get("dog").unwrap());
+
+        // a folder in place of the index file makes it unwritable
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+        fs::remove_file(&index_file_path).expect("remove index file");
+        fs::create_dir(&index_file_path).expect("create folder in place of index file");
+
+        match store.rename("dog", "goat") {
+            Ok(_) => panic!("rename should fail when the index file is unwritable"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        assert_eq!("23 months", store.get("dog").unwrap());
+        assert_eq!("678 months", store.get("goat").unwrap());
+
+        fs::remove_dir(&index_file_path).expect("remove folder in place of index file");
+    }
+
+    #[test]
+    #[serial]
+    fn rename_non_existent_key_returns_not_found_error() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        match store.rename("non-existent", "foo") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+
+        assert_eq!(None, store.index.get("foo"));
+    }
+
+    #[test]
+    #[serial]
+    fn touch_old_key_moves_value_to_memtable_so_reads_are_served_from_it() {
+        let (key, value) = ("cow", "500 months");
+        let old_timestamped_key = "1655375120328185000-cow";
+        let del_file_path = Path::new(DB_PATH).join(DEL_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.touch(key).expect("touch cow");
+
+        let new_timestamped_key = store.index.get(key).unwrap().clone();
+        let del_file_content = fs::read_to_string(&del_file_path).expect("read del file");
+
+        assert!(new_timestamped_key > store.current_log_file);
+        assert_eq!(value, store.memtable.get(&new_timestamped_key).unwrap());
+        assert!(del_file_content.contains(old_timestamped_key));
+
+        store.cache = Cache::new_empty();
+        assert_eq!(value, store.get(key).unwrap());
+        assert!(store.last_read_was_cached());
+        assert!(store.cache.is_empty());
+
+        store.vacuum().expect("vacuum");
+        assert_eq!(value, store.get(key).unwrap());
+
+        // keys already in the memtable keep their timestamped keys
+        store.touch(key).expect("touch cow again");
+        assert_eq!(&new_timestamped_key, store.index.get(key).unwrap());
+
+        match store.touch("non-existent") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn touch_with_unwritable_files_keeps_key_readable() {
+        let del_file_path = Path::new(DB_PATH).join(DEL_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        // a folder in place of the log file makes it unwritable
+        let log_file_path = store.current_log_file_path.clone();
+        let log_file_content = fs::read_to_string(&log_file_path).expect("read log file");
+        fs::remove_file(&log_file_path).expect("remove log file");
+        fs::create_dir(&log_file_path).expect("create folder in place of log file");
+
+        assert!(store.touch("cow").is_err());
+        let del_file_content = fs::read_to_string(&del_file_path).expect("read del file");
+
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert!(!del_file_content.contains("1655375120328185000-cow"));
+        assert!(!store.is_degraded());
+
+        fs::remove_dir(&log_file_path).expect("remove folder in place of log file");
+        fs::write(&log_file_path, log_file_content).expect("restore log file");
+
+        // a folder in place of the index file makes it unwritable
+        let index_file_path = Path::new(DB_PATH).join(INDEX_FILENAME);
+        fs::remove_file(&index_file_path).expect("remove index file");
+        fs::create_dir(&index_file_path).expect("create folder in place of index file");
+
+        match store.touch("cow") {
+            Ok(_) => panic!("touch should fail when the index file is unwritable"),
+            Err(err) => assert!(err.to_string().contains("degraded")),
+        }
+        let del_file_content = fs::read_to_string(&del_file_path).expect("read del file");
+
+        assert_eq!("500 months", store.get("cow").unwrap());
+        assert_eq!("1655375120328185000-cow", store.index.get("cow").unwrap());
+        assert!(!del_file_content.contains("1655375120328185000-cow"));
+
+        fs::remove_dir(&index_file_path).expect("remove folder in place of index file");
+    }
+
+    #[test]
+    #[serial]
+    fn clear_prefix_removes_matching_keys_from_index_and_adds_them_to_del_file() {
+        let expected_index = HashMap::from([
+            (String::from("cow"), String::from("1655375120328185000-cow")),
+            (String::from("dog"), String::from("1655375120328185100-dog")),
+            (
+                String::from("goat"),
+                String::from("1655404770518678000-goat"),
+            ),
+            (String::from("hen"), String::from("1655404670510698000-hen")),
+        ]);
+        let mut expected_keys_marked_for_delete =
+            vec!["1655404770534578000-pig", "1655403775538278000-fish"];
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
         let db_path = Path::new(DB_PATH);
         let index_file_path = db_path.join(INDEX_FILENAME);
         let del_file_path = db_path.join(DEL_FILENAME);
-        let empty_map: HashMap<String, String> = Default::default();
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
+        store.set("pigeon", "1 month").expect("set pigeon");
+        store.delete("pigeon").expect("delete pigeon");
+        fs::write(&del_file_path, "").expect("clear delete file");
 
-        let current_log_filename = format!("{}.log", store.current_log_file);
-        expected_files.push(current_log_filename.clone());
-        let expected_log_file_path = OsString::from(Path::new(DB_PATH).join(current_log_filename));
-        let mut actual_files =
-            utils::get_file_names_in_folder(DB_PATH).expect("get files in db folder");
+        let removed_by_p = store.clear_prefix("pi").expect("clear prefix pi");
+        let removed_by_f = store.clear_prefix("f").expect("clear prefix f");
+        let removed_by_x = store.clear_prefix("x").expect("clear prefix x");
 
-        actual_files.sort();
-        expected_files.sort();
+        let idx_file_content = fs::read_to_string(index_file_path).expect("read index file");
+        let del_file_content = fs::read_to_string(del_file_path).expect("read del file");
+        let map_from_idx_file = utils::extract_key_values_from_str(&idx_file_content)
+            .expect("extract key values from index");
+        let mut list_from_del_file = utils::extract_tokens_from_str(&del_file_content);
+        expected_keys_marked_for_delete.sort();
+        list_from_del_file.sort();
 
-        assert_eq!(expected_cache, store.cache);
-        assert_ne!("".to_string(), store.current_log_file);
-        assert_eq!(empty_map, store.index);
-        assert_eq!(empty_map, store.memtable);
-        assert_eq!(EMPTY_LIST, store.data_files);
-        assert_eq!(expected_files, actual_files);
-        assert_eq!(index_file_path, store.index_file_path);
-        assert_eq!(expected_log_file_path, store.current_log_file_path);
-        assert_eq!(del_file_path, store.del_file_path);
+        assert_eq!(1, removed_by_p);
+        assert_eq!(1, removed_by_f);
+        assert_eq!(0, removed_by_x);
+        assert_eq!(expected_index, map_from_idx_file);
+        assert_eq!(expected_keys_marked_for_delete, list_from_del_file);
+        assert_eq!(expected_index, store.index);
+    }
+
+    #[test]
+    #[serial]
+    fn status_distinguishes_present_deleted_and_absent_keys() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.delete("pig").expect("delete pig");
+
+        assert_eq!(KeyStatus::Present, store.status("cow").unwrap());
+        assert_eq!(KeyStatus::Deleted, store.status("pig").unwrap());
+        assert_eq!(KeyStatus::Absent, store.status("non-existent").unwrap());
+
+        store.vacuum().expect("vacuum");
+
+        assert_eq!(KeyStatus::Absent, store.status("pig").unwrap());
+
+        store.set("pig", "2 months").expect("set pig");
+
+        assert_eq!(KeyStatus::Present, store.status("pig").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn undelete_restores_deleted_keys_till_they_are_vacuumed() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let pig_timestamped_key = store.index["pig"].clone();
+        let cow_timestamped_key = store.index["cow"].clone();
+        store.delete("pig").expect("delete pig");
+        store.delete("cow").expect("delete cow");
+        store.delete("dog").expect("delete dog");
+
+        store.undelete("pig").expect("undelete pig");
+        store.undelete("cow").expect("undelete cow");
+
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+        assert_eq!(&pig_timestamped_key, &store.index["pig"]);
+        assert_eq!(&cow_timestamped_key, &store.index["cow"]);
+        assert_eq!(KeyStatus::Deleted, store.status("dog").unwrap());
+
+        // the restored keys survive a vacuum and a reload
+        store.vacuum().expect("vacuums store");
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.load().expect("reloads store");
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+
+        // vacuumed, present or never-set keys cannot be undeleted
+        for key in ["dog", "cow", "non-existent"] {
+            let err = store.undelete(key).expect_err(key);
+            assert!(err.to_string().contains("not found"), "{}", key);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn undelete_restores_deleted_keys_after_their_files_are_rewritten() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        store.delete("pig").expect("delete pig");
+        store.delete("cow").expect("delete cow");
+        // rewrite the log file holding pig and the data file holding cow
+        store.set("goat", "1 month").expect("set goat");
+        store.set("dog", "2 months").expect("set dog");
+
+        store.undelete("pig").expect("undelete pig");
+        store.undelete("cow").expect("undelete cow");
+
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        store.load().expect("reloads store");
+        assert_eq!("70 months", store.get("pig").expect("get pig"));
+        assert_eq!("500 months", store.get("cow").expect("get cow"));
+        assert_eq!("2 months", store.get("dog").expect("get dog"));
+    }
+
+    #[test]
+    #[serial]
+    fn undelete_restores_deleted_keys_whose_sets_were_buffered() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+        store.set_flush_every_n(Some(10));
+
+        store.set("fish", "8 months").expect("set fish");
+        store.delete("fish").expect("delete fish");
+        store.undelete("fish").expect("undelete fish");
+
+        assert_eq!("8 months", store.get("fish").expect("get fish"));
+    }
+
+    #[test]
+    #[serial]
+    fn delete_key_returns_deleted_value_from_memtable_or_data_file() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        assert_eq!("70 months", store.delete("pig").expect("delete pig"));
+        assert_eq!("500 months", store.delete("cow").expect("delete cow"));
+    }
+
+    #[test]
+    #[serial]
+    fn delete_non_existent_key_returns_not_found_error() {
+        let key = "non-existent";
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        match store.delete(key) {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("not found")),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn delete_key_with_missing_data_file_returns_error_and_keeps_key() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        fs::remove_file(Path::new(DB_PATH).join(DATA_FILES[0])).expect("remove data file");
+
+        match store.delete("cow") {
+            Ok(_) => panic!("error was expected"),
+            Err(err) => assert!(err.to_string().contains("corrupted data")),
+        }
+        assert!(store.index.contains_key("cow"));
+        assert_eq!("678 months", store.delete("goat").expect("delete goat"));
+    }
+
+    #[test]
+    #[serial]
+    fn filter_returns_key_values_matching_predicate_in_creation_order() {
+        let expected = vec![
+            ("cow".to_string(), "500 months".to_string()),
+            ("hen".to_string(), "567 months".to_string()),
+            ("goat".to_string(), "678 months".to_string()),
+        ];
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+
+        let results = store
+            .filter(&|_, value| value.len() == "500 months".len())
+            .expect("filter");
+        let no_results = store.filter(&|key, _| key == "bar").expect("filter");
+
+        assert_eq!(expected, results);
+        assert_eq!(Vec::<(String, String)>::new(), no_results);
+    }
+
+    #[test]
+    #[serial]
+    fn scan_glob_returns_key_values_with_matching_keys_in_creation_order() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.set("price*", "10").expect("set price*");
+        store.set("price1", "20").expect("set price1");
+
+        let with_o = store.scan_glob("*o*").expect("scan *o*");
+        let three_letters = store.scan_glob("?o?").expect("scan ?o?");
+        let literal_star = store.scan_glob(r"price\*").expect(r"scan price\*");
+        let any_price = store.scan_glob("price?").expect("scan price?");
+        let deleted = store.scan_glob("ba?").expect("scan ba?");
+
+        assert_eq!(
+            vec![
+                ("cow".to_string(), "500 months".to_string()),
+                ("dog".to_string(), "23 months".to_string()),
+                ("goat".to_string(), "678 months".to_string()),
+            ],
+            with_o
+        );
+        assert_eq!(
+            vec![
+                ("cow".to_string(), "500 months".to_string()),
+                ("dog".to_string(), "23 months".to_string()),
+            ],
+            three_letters
+        );
+        assert_eq!(vec![("price*".to_string(), "10".to_string())], literal_star);
+        assert_eq!(
+            vec![
+                ("price*".to_string(), "10".to_string()),
+                ("price1".to_string(), "20".to_string()),
+            ],
+            any_price
+        );
+        assert_eq!(Vec::<(String, String)>::new(), deleted);
+    }
+
+    #[test]
+    #[serial]
+    fn contains_value_finds_values_in_memtable_and_data_files() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.set("sheep", "500 months").expect("set sheep");
+
+        assert!(store.contains_value("500 months").unwrap());
+        assert!(store.contains_value("567 months").unwrap());
+        assert!(!store.contains_value("foo").unwrap());
+        assert!(!store.contains_value("1000 months").unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn contains_value_stops_at_the_first_match() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        // goat was created after cow so its unreadable value is only reached if the scan goes on
+        store.memtable.remove("1655404770518678000-goat");
+
+        assert!(store.contains_value("500 months").unwrap());
+        assert!(store.contains_value("1000 months").is_err());
     }
 
     #[test]
     #[serial]
-    fn set_new_key_adds_key_value_to_memtable_and_index_and_log_files() {
-        let (key, value) = ("New key", "foo");
+    fn verify_finds_no_inconsistencies_in_consistent_store() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
-        let db_path = Path::new(DB_PATH);
-        let index_file_path = db_path.join(INDEX_FILENAME);
-        let log_file_path = db_path.join(LOG_FILENAME);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
-        store
-            .set(key, value)
-            .expect(&format!("set key: {}, value: {}", key, value));
-
-        // expected
-        let timestamped_key = store.index.get(key).unwrap();
-        let expected_index_file_entry = format!(
-            "{}{}{}{}",
-            key, KEY_VALUE_SEPARATOR, timestamped_key, TOKEN_SEPARATOR
-        );
-        let expected_log_file_entry = format!(
-            "{}{}{}{}",
-            timestamped_key, KEY_VALUE_SEPARATOR, value, TOKEN_SEPARATOR
-        );
+        store.delete("cow").expect("delete cow");
 
-        // actual
-        let value_in_memtable = store.memtable.get(timestamped_key).unwrap();
-        let index_file_content = fs::read_to_string(index_file_path).expect("read index file");
-        let log_file_content = fs::read_to_string(log_file_path).expect("read log file");
+        let report = store.verify().expect("verify");
 
-        assert_eq!(value, value_in_memtable);
-        assert!(index_file_content.contains(&expected_index_file_entry));
-        assert!(log_file_content.contains(&expected_log_file_entry));
+        assert!(report.is_ok(), "{:?}", report);
     }
 
     #[test]
     #[serial]
-    fn set_same_recent_key_updates_value_in_memtable_and_log_file() {
-        let (key, value, new_value) = ("New key", "foo", "hello-world");
+    fn verify_finds_values_missing_from_memtable_and_data_files() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
-        let db_path = Path::new(DB_PATH);
-        let index_file_path = db_path.join(INDEX_FILENAME);
-        let log_file_path = db_path.join(LOG_FILENAME);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
-        store
-            .set(key, value)
-            .expect(&format!("set key: {}, value: {}", key, value));
-        store
-            .set(key, new_value)
-            .expect(&format!("set key: {}, value: {}", key, new_value));
-
-        // expected
-        let timestamped_key = store.index.get(key).unwrap();
-        let expected_index_file_entry = format!(
-            "{}{}{}{}",
-            key, KEY_VALUE_SEPARATOR, timestamped_key, TOKEN_SEPARATOR
-        );
-        let expected_log_file_entry = format!(
-            "{}{}{}{}",
-            timestamped_key, KEY_VALUE_SEPARATOR, new_value, TOKEN_SEPARATOR
+        store.memtable.remove("1655404770518678000-goat");
+        fs::write(
+            Path::new(DB_PATH).join(DATA_FILES[0]),
+            "1655375120328185100-dog><?&(^#23 months$%#@*&^&",
+        )
+        .expect("write data file");
+
+        let report = store.verify().expect("verify");
+
+        assert_eq!(
+            vec![
+                Inconsistency::MissingValue {
+                    key: "cow".to_string(),
+                    timestamped_key: "1655375120328185000-cow".to_string(),
+                    file_name: DATA_FILES[0].to_string(),
+                },
+                Inconsistency::MissingValue {
+                    key: "goat".to_string(),
+                    timestamped_key: "1655404770518678000-goat".to_string(),
+                    file_name: LOG_FILENAME.to_string(),
+                },
+            ],
+            report.inconsistencies
         );
+    }
 
-        // actual
-        let value_in_memtable = store.memtable.get(timestamped_key).unwrap();
-        let index_file_content = fs::read_to_string(index_file_path).expect("read index file");
-        let log_file_content = fs::read_to_string(log_file_path).expect("read log file");
+    #[test]
+    #[serial]
+    fn verify_finds_keys_in_index_that_are_listed_in_del_file() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
-        assert_eq!(new_value, value_in_memtable);
-        assert!(index_file_content.contains(&expected_index_file_entry));
-        assert!(log_file_content.contains(&expected_log_file_entry));
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        utils::append_to_file(
+            Path::new(DB_PATH).join(DEL_FILENAME),
+            &format!("1655404770534578000-pig{}", TOKEN_SEPARATOR),
+        )
+        .expect("append to del file");
+
+        let report = store.verify().expect("verify");
+
+        assert_eq!(
+            vec![Inconsistency::DeletedKeyInIndex {
+                key: "pig".to_string(),
+                timestamped_key: "1655404770534578000-pig".to_string(),
+            }],
+            report.inconsistencies
+        );
     }
 
     #[test]
     #[serial]
-    fn set_old_key_updates_value_in_cache_and_in_cky_file() {
-        let (key, value) = ("cow", "foo-again");
-        let db_path = Path::new(DB_PATH);
-        let data_file_path = db_path.join(DATA_FILES[0]);
+    fn verify_finds_keys_for_which_there_is_no_data_file() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
         store.load().expect("loads store");
         store
-            .set(key, value)
-            .expect(&format!("set key: {}, value: {}", key, value));
-
-        // expected
-        let timestamped_key = store.index.get(key).unwrap();
-        let expected_data_file_entry =
-            format!("{}{}{}", timestamped_key, KEY_VALUE_SEPARATOR, value);
+            .index
+            .insert("ancient".to_string(), "1-ancient".to_string());
 
-        // actual
-        let value_in_cache = store.cache.get(timestamped_key).unwrap();
-        let data_file_content = fs::read_to_string(data_file_path).expect("read data file");
+        let report = store.verify().expect("verify");
 
-        assert_eq!(value, value_in_cache);
-        assert!(data_file_content.contains(&expected_data_file_entry));
+        assert_eq!(
+            vec![Inconsistency::NoFileForKey {
+                key: "ancient".to_string(),
+                timestamped_key: "1-ancient".to_string(),
+            }],
+            report.inconsistencies
+        );
     }
 
     #[test]
     #[serial]
-    fn get_new_key_gets_value_from_memtable() {
-        let (key, expected_value) = ("fish", "8990 months");
+    fn verify_finds_unreadable_data_files() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
+        fs::write(Path::new(DB_PATH).join(DATA_FILES[0]), "malformed$%#@*&^&")
+            .expect("write data file");
 
-        // remove the database files to show data is got straight from memory
-        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        let report = store.verify().expect("verify");
 
-        let actual_value = store.get(key).unwrap();
-        assert_eq!(actual_value, expected_value);
+        assert_eq!(
+            vec![Inconsistency::UnreadableFile {
+                file_name: DATA_FILES[0].to_string(),
+            }],
+            report.inconsistencies
+        );
     }
 
     #[test]
     #[serial]
-    fn get_old_key_updates_cache_from_disk_and_gets_value_from_cache() {
-        let (key, expected_value) = ("cow", "500 months");
-        let expected_initial_cache = Cache::new_empty();
-        let expected_final_cache = Cache::new(
-            HashMap::from([
-                (
-                    String::from("1655375120328185000-cow"),
-                    String::from("500 months"),
-                ),
-                (
-                    String::from("1655375120328185100-dog"),
-                    String::from("23 months"),
-                ),
-            ]),
-            DATA_FILES[0].trim_end_matches(".cky"),
-            DATA_FILES[1].trim_end_matches(".cky"),
-        );
-
+    fn keys_count_by_location_splits_keys_between_memtable_and_data_files() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
 
-        let initial_cache = store.cache.clone();
-        let value = store.get(key).unwrap();
-        let final_cache = store.cache.clone();
-
-        assert_eq!(expected_value, value);
-        assert_eq!(expected_initial_cache, initial_cache);
-        assert_eq!(expected_final_cache, final_cache);
+        let expected = KeyLocationCounts {
+            memtable: 4,
+            data_files: 2,
+        };
+        assert_eq!(expected, store.keys_count_by_location());
     }
 
     #[test]
     #[serial]
-    fn get_old_key_again_gets_value_straight_from_cache() {
-        let (key, expected_value) = ("cow", "500 months");
+    fn stats_counts_keys_data_files_pending_deletes_and_cache_hits_and_misses() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
-        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data to db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.set_vacuum_on_load(false);
         store.load().expect("loads store");
 
-        let _ = store.get(key).unwrap();
+        store.get("cow").expect("get cow");
+        store.get("dog").expect("get dog");
+        store.get("goat").expect("get goat");
+
+        let expected = Stats {
+            keys: 6,
+            data_files: 2,
+            size_on_disk_bytes: store.size_on_disk().expect("size on disk"),
+            pending_deletes: 2,
+            cache_hits: 2,
+            cache_misses: 1,
+        };
+        assert_eq!(expected, store.stats().expect("stats"));
+        assert_eq!(
+            format!("{{\"keys\":6,\"data_files\":2,\"size_on_disk_bytes\":{},\"pending_deletes\":2,\"cache_hits\":2,\"cache_misses\":1}}", expected.size_on_disk_bytes),
+            expected.to_json()
+        );
+    }
 
-        // remove the database files to show data is got straight from memory on next get
-        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+    #[test]
+    #[serial]
+    fn fragmentation_returns_ratio_of_bytes_marked_for_deletion_per_file() {
+        let log_file_content = "1655404770518678000-goat><?&(^#678 months$%#@*&^&1655404670510698000-hen><?&(^#567 months$%#@*&^&1655404770534578000-pig><?&(^#70 months$%#@*&^&1655403775538278000-fish><?&(^#8990 months$%#@*&^&1655403795838278000-foo><?&(^#890 months$%#@*&^&";
+        let deleted_log_file_entry = "1655403795838278000-foo><?&(^#890 months$%#@*&^&";
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
-        let value = store.get(key).unwrap();
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.set_vacuum_on_load(false);
+        store.load().expect("loads store");
 
-        assert_eq!(expected_value, value);
+        let expected = vec![
+            (DATA_FILES[0].to_string(), 0.0),
+            (DATA_FILES[1].to_string(), 1.0),
+            (
+                "01655375171402014000.log".to_string(),
+                deleted_log_file_entry.len() as f64 / log_file_content.len() as f64,
+            ),
+        ];
+        assert_eq!(expected, store.fragmentation().expect("fragmentation"));
+
+        store.vacuum().expect("vacuums store");
+        let ratios = store.fragmentation().expect("fragmentation after vacuum");
+        assert!(
+            ratios.iter().all(|(_, ratio)| *ratio == 0.0),
+            "{:?}",
+            ratios
+        );
     }
 
     #[test]
     #[serial]
-    fn get_non_existent_key_returns_not_found_error() {
-        let key = "non-existent";
+    fn pending_deletes_counts_distinct_keys_in_del_file_till_vacuum() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.set_vacuum_on_load(false);
         store.load().expect("loads store");
+        assert_eq!(2, store.pending_deletes().expect("count pending deletes"));
+
+        store.delete("cow").expect("delete cow");
+        store.delete("pig").expect("delete pig");
+        // a key listed twice is counted once
+        let del_file_content = fs::read_to_string(&store.del_file_path).expect("read del file");
+        fs::write(
+            &store.del_file_path,
+            format!("{}1655403795838278000-foo$%#@*&^&", del_file_content),
+        )
+        .expect("write del file");
+        assert_eq!(4, store.pending_deletes().expect("count pending deletes"));
+
+        store.vacuum().expect("vacuums store");
+        assert_eq!(0, store.pending_deletes().expect("count pending deletes"));
+    }
 
-        match store.get(key) {
-            Ok(_) => panic!("error was expected"),
-            Err(err) => assert!(err.to_string().contains("not found")),
-        }
+    #[test]
+    #[serial]
+    fn iter_data_file_returns_key_values_in_data_file_without_touching_cache() {
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        store.load().expect("loads store");
+        store.get("cow").expect("get cow");
+        let cache_before = store.cache.clone();
+
+        let expected = vec![
+            (
+                "1655375120328185000-cow".to_string(),
+                "500 months".to_string(),
+            ),
+            (
+                "1655375120328185100-dog".to_string(),
+                "23 months".to_string(),
+            ),
+        ];
+        assert_eq!(
+            expected,
+            store
+                .iter_data_file(DATA_FILES[0])
+                .expect("iter first data file")
+        );
+        assert_eq!(cache_before, store.cache);
+        assert!(store.iter_data_file("00000000000000000001.cky").is_err());
     }
 
     #[test]
     #[serial]
-    fn delete_key_removes_key_from_index_and_adds_it_to_del_file() {
-        let key = "pig";
-        let expected_index = HashMap::from([
-            (String::from("cow"), String::from("1655375120328185000-cow")),
-            (String::from("dog"), String::from("1655375120328185100-dog")),
-            (String::from("goat"), String::from("1655404770518678-goat")),
-            (String::from("hen"), String::from("1655404670510698-hen")),
-            (String::from("fish"), String::from("1655403775538278-fish")),
-        ]);
-        let expected_keys_marked_for_delete = vec!["1655404770534578-pig"];
+    fn recent_entries_returns_key_values_in_memtable_in_creation_order() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
-        let db_path = Path::new(DB_PATH);
-        let index_file_path = db_path.join(INDEX_FILENAME);
-        let del_file_path = db_path.join(DEL_FILENAME);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
         utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
         store.load().expect("loads store");
-        store.delete(key).expect(&format!("delete {}", key));
 
-        let idx_file_content = fs::read_to_string(index_file_path).expect("read index file");
-        let del_file_content = fs::read_to_string(del_file_path).expect("read del file");
-        let map_from_idx_file = utils::extract_key_values_from_str(&idx_file_content)
-            .expect("extract key values from index");
-        let list_from_del_file = utils::extract_tokens_from_str(&del_file_content);
+        store.set("sheep", "2 months").expect("set sheep");
+        store.set("pig", "71 months").expect("update pig");
+        store.set("cow", "501 months").expect("update cow");
+        store.delete("fish").expect("delete fish");
 
-        match store.get(key) {
-            Ok(_) => panic!("error was expected"),
-            Err(err) => assert!(err.to_string().contains("not found")),
+        let expected = vec![
+            ("hen".to_string(), "567 months".to_string()),
+            ("goat".to_string(), "678 months".to_string()),
+            ("pig".to_string(), "71 months".to_string()),
+            ("sheep".to_string(), "2 months".to_string()),
+        ];
+        assert_eq!(expected, store.recent_entries());
+    }
+
+    #[test]
+    #[serial]
+    fn shrink_memory_releases_capacity_after_deleting_many_keys() {
+        let mut store = Store::new(DB_PATH, 1024.0);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        let keys: Vec<String> = (0..1000).map(|i| format!("key{}", i)).collect();
+        store
+            .set_many_from_iter(&mut keys.iter().map(|k| (k.clone(), "v".to_string())))
+            .expect("set many");
+        store
+            .clear_prefix("key")
+            .expect("clear keys with prefix key");
+
+        let index_capacity_before = store.index.capacity();
+        let memtable_capacity_before = store.memtable.capacity();
+
+        store.shrink_memory();
+
+        assert!(store.index.capacity() < index_capacity_before);
+        assert!(store.memtable.capacity() < memtable_capacity_before);
+        assert_eq!(0, store.index.len());
+    }
+
+    #[test]
+    #[serial]
+    fn merge_from_sets_other_database_key_values_according_to_policy() {
+        let other_db_path = "test_store_other_db";
+
+        for (policy, expected_cow, expected_count) in [
+            (MergePolicy::KeepMine, "500 months", 1),
+            (MergePolicy::TakeTheirs, "1 month", 2),
+        ] {
+            let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+            let mut other = Store::new(other_db_path, MAX_FILE_SIZE_KB);
+
+            utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+            utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+            utils::clear_dummy_file_data_in_db(other_db_path).expect("clears other db");
+            store.load().expect("loads store");
+            other.load().expect("loads other store");
+            other.set("cow", "1 month").expect("set cow in other");
+            other.set("sheep", "2 months").expect("set sheep in other");
+            // without a log file, loading the other database would create one
+            other.checkpoint().expect("checkpoint other");
+            let other_log_file_path =
+                Path::new(other_db_path).join(format!("{}.log", other.current_log_file));
+            fs::remove_file(other_log_file_path).expect("remove other log file");
+            let other_files_before =
+                utils::get_file_names_in_folder(other_db_path).expect("get other files");
+
+            let count = store
+                .merge_from(other_db_path, policy)
+                .expect("merge from other");
+            let other_files_after =
+                utils::get_file_names_in_folder(other_db_path).expect("get other files");
+
+            assert_eq!(expected_count, count, "{:?}", policy);
+            assert_eq!(expected_cow, store.get("cow").unwrap(), "{:?}", policy);
+            assert_eq!("2 months", store.get("sheep").unwrap());
+            assert_eq!("23 months", store.get("dog").unwrap());
+            assert_eq!("1 month", other.get("cow").unwrap());
+            assert!(other.get("dog").is_err());
+            assert_eq!(other_files_before, other_files_after);
         }
 
-        assert_eq!(expected_index, map_from_idx_file);
-        assert_eq!(expected_keys_marked_for_delete, list_from_del_file);
-        assert_eq!(expected_index, store.index);
+        utils::clear_dummy_file_data_in_db(other_db_path).expect("clears other db");
     }
 
     #[test]
     #[serial]
-    fn delete_non_existent_key_returns_not_found_error() {
-        let key = "non-existent";
+    fn merge_from_non_database_returns_not_a_database_error() {
         let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
         store.load().expect("loads store");
 
-        match store.delete(key) {
+        match store.merge_from("test_store_non_existent_db", MergePolicy::TakeTheirs) {
             Ok(_) => panic!("error was expected"),
-            Err(err) => assert!(err.to_string().contains("not found")),
+            Err(err) => assert!(err.to_string().contains("not a ckydb database")),
         }
+
+        assert!(!Path::new("test_store_non_existent_db").exists());
     }
 
     #[test]
@@ -952,7 +5485,7 @@ mod test {
     #[test]
     #[serial]
     fn vacuum_removes_keys_and_values_listed_in_del_file_from_log_and_cky_files() {
-        let expected_log_file_content = String::from("1655404770518678-goat><?&(^#678 months$%#@*&^&1655404670510698-hen><?&(^#567 months$%#@*&^&1655404770534578-pig><?&(^#70 months$%#@*&^&1655403775538278-fish><?&(^#8990 months$%#@*&^&");
+        let expected_log_file_content = String::from("1655404770518678000-goat><?&(^#678 months$%#@*&^&1655404670510698000-hen><?&(^#567 months$%#@*&^&1655404770534578000-pig><?&(^#70 months$%#@*&^&1655403775538278000-fish><?&(^#8990 months$%#@*&^&");
         let expected_data_contents = vec![
             "1655375120328185000-cow><?&(^#500 months$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&".to_string(), "".to_string(),
         ];
@@ -961,7 +5494,7 @@ mod test {
         let data_file_paths = DATA_FILES.map(|f| db_path.join(f));
         let log_file_path = db_path.join(LOG_FILENAME);
         let del_file_path = db_path.join(DEL_FILENAME);
-        let store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
             panic!("error clearing dummy data: {}", err);
@@ -985,10 +5518,46 @@ mod test {
         assert_eq!(expected_data_contents, data_file_content);
     }
 
+    #[test]
+    #[serial]
+    fn vacuum_ignores_empty_and_malformed_entries_in_del_file() {
+        let expected_log_file_content = String::from("1655404770518678000-goat><?&(^#678 months$%#@*&^&1655404670510698000-hen><?&(^#567 months$%#@*&^&1655404770534578000-pig><?&(^#70 months$%#@*&^&1655403775538278000-fish><?&(^#8990 months$%#@*&^&");
+        let expected_data_contents = vec![
+            "1655375120328185000-cow><?&(^#500 months$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&".to_string(), "".to_string(),
+        ];
+        let expected_keys_to_delete = vec!["1655403795838278000-foo", "1655375171402014000-bar"];
+        let messy_del_file_content = "$%#@*&^&1655403795838278000-foo$%#@*&^&$%#@*&^& $%#@*&^&-cow$%#@*&^&cow$%#@*&^&1655375120328185000-$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&1655375171402014000-bar$%#@*&^&";
+        let db_path = Path::new(DB_PATH);
+        let data_file_paths = DATA_FILES.map(|f| db_path.join(f));
+        let log_file_path = db_path.join(LOG_FILENAME);
+        let del_file_path = db_path.join(DEL_FILENAME);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        utils::add_dummy_file_data_in_db(DB_PATH).expect("adds dummy data in db");
+        fs::write(&del_file_path, messy_del_file_content).expect("write messy del file");
+
+        let keys_to_delete = store.get_keys_to_delete().expect("get keys to delete");
+
+        if let Err(err) = store.vacuum() {
+            panic!("error vacuuming: {}", err);
+        }
+
+        let data_file_content =
+            data_file_paths.map(|path| fs::read_to_string(path).expect("read data file"));
+        let log_file_content = fs::read_to_string(log_file_path).expect("read log file");
+        let del_file_content = fs::read_to_string(del_file_path).expect("read del file");
+
+        assert_eq!(expected_keys_to_delete, keys_to_delete);
+        assert_eq!(expected_log_file_content, log_file_content);
+        assert_eq!("", del_file_content);
+        assert_eq!(expected_data_contents, data_file_content);
+    }
+
     #[test]
     #[serial]
     fn vacuum_does_nothing_if_del_file_is_empty() {
-        let expected_log_file_content = String::from("1655404770518678-goat><?&(^#678 months$%#@*&^&1655404670510698-hen><?&(^#567 months$%#@*&^&1655404770534578-pig><?&(^#70 months$%#@*&^&1655403775538278-fish><?&(^#8990 months$%#@*&^&1655403795838278-foo><?&(^#890 months$%#@*&^&");
+        let expected_log_file_content = String::from("1655404770518678000-goat><?&(^#678 months$%#@*&^&1655404670510698000-hen><?&(^#567 months$%#@*&^&1655404770534578000-pig><?&(^#70 months$%#@*&^&1655403775538278000-fish><?&(^#8990 months$%#@*&^&1655403795838278000-foo><?&(^#890 months$%#@*&^&");
         let expected_data_contents = vec![
             "1655375120328185000-cow><?&(^#500 months$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&".to_string(), "1655375171402014000-bar><?&(^#foo$%#@*&^&".to_string(),
         ];
@@ -997,7 +5566,7 @@ mod test {
         let data_file_paths = DATA_FILES.map(|f| db_path.join(f));
         let log_file_path = db_path.join(LOG_FILENAME);
         let del_file_path = db_path.join(DEL_FILENAME);
-        let store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
+        let mut store = Store::new(DB_PATH, MAX_FILE_SIZE_KB);
 
         if let Err(err) = utils::clear_dummy_file_data_in_db(DB_PATH) {
             panic!("error clearing dummy data: {}", err);
@@ -1023,4 +5592,93 @@ mod test {
         assert_eq!(expected_del_file_content, del_file_content);
         assert_eq!(expected_data_contents, data_file_content);
     }
+
+    #[test]
+    #[serial]
+    fn vacuum_removes_deleted_keys_from_many_data_files() {
+        let number_of_keys = 40;
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(2));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        for i in 0..number_of_keys {
+            store
+                .set(&format!("key-{}", i), &format!("value-{}", i))
+                .expect("set key");
+        }
+        for i in (0..number_of_keys).step_by(2) {
+            store.delete(&format!("key-{}", i)).expect("delete key");
+        }
+        assert_eq!(number_of_keys / 2, store.data_files.len());
+
+        store.vacuum().expect("vacuums store");
+
+        let data_file_contents =
+            utils::read_files_with_extension(DB_PATH, "cky").expect("read data files");
+        let remaining_values: Vec<String> = data_file_contents
+            .iter()
+            .flat_map(|content| {
+                utils::extract_key_values_from_str(content)
+                    .expect("parse data file")
+                    .into_values()
+            })
+            .collect();
+        assert_eq!(number_of_keys / 2, remaining_values.len());
+        assert!(remaining_values.iter().all(|value| value
+            .trim_start_matches("value-")
+            .parse::<usize>()
+            .unwrap()
+            % 2
+            == 1));
+        assert_eq!("", fs::read_to_string(&store.del_file_path).unwrap());
+
+        for i in (1..number_of_keys).step_by(2) {
+            assert_eq!(
+                format!("value-{}", i),
+                store.get(&format!("key-{}", i)).expect("get key")
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn vacuum_coalesces_adjacent_small_data_files_and_keeps_keys_readable() {
+        let keys = ["cow", "dog", "goat", "hen", "pig"];
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.set_clock(Box::new(MockClock::new(1000, 10)));
+        store.set_max_log_entries(Some(1));
+        store.set_min_data_file_kb(Some(1.0));
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        store.load().expect("loads store");
+
+        for key in keys {
+            store.set(key, &format!("{} value", key)).expect("set key");
+        }
+        assert_eq!(keys.len(), store.data_files.len());
+
+        store.vacuum().expect("vacuums store");
+
+        let first_data_file = utils::format_timestamp(1000);
+        assert_eq!(vec![first_data_file.clone()], store.data_files);
+        let data_files = utils::get_file_names_in_folder(DB_PATH)
+            .expect("get files in db folder")
+            .into_iter()
+            .filter(|name| name.ends_with(".cky"))
+            .collect::<Vec<String>>();
+        assert_eq!(vec![format!("{}.cky", first_data_file)], data_files);
+
+        for key in keys {
+            assert_eq!(format!("{} value", key), store.get(key).expect("get key"));
+        }
+
+        let mut store = Store::new(DB_PATH, 1024.0);
+        store.load().expect("reloads store");
+        for key in keys {
+            assert_eq!(format!("{} value", key), store.get(key).expect("get key"));
+        }
+    }
 }
@@ -1,19 +1,21 @@
-use crate::constants::{KEY_VALUE_SEPARATOR, TOKEN_SEPARATOR};
+use crate::constants::{KEY_VALUE_SEPARATOR, TIMESTAMP_WIDTH, TOKEN_SEPARATOR};
 use crate::errors::CorruptedDataError;
+use crate::options::RetryPolicy;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::ErrorKind::AlreadyExists;
-use std::io::{self, ErrorKind, ErrorKind::NotFound, Write};
+use std::io::{self, ErrorKind::NotFound, Write};
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
 
 const DUMMY_FILE_DATA: [(&str, &str); 5] = [
-    ("1655375120328185000.cky", "1655375120328185000-cow><?&(^#500 months$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&"),
-    ("1655375120328186000.cky", "1655375171402014000-bar><?&(^#foo$%#@*&^&"),
-    ("1655375171402014000.log", "1655404770518678-goat><?&(^#678 months$%#@*&^&1655404670510698-hen><?&(^#567 months$%#@*&^&1655404770534578-pig><?&(^#70 months$%#@*&^&1655403775538278-fish><?&(^#8990 months$%#@*&^&1655403795838278-foo><?&(^#890 months$%#@*&^&"),
-    ("delete.del", "1655403795838278-foo$%#@*&^&1655375171402014000-bar$%#@*&^&"),
-    ("index.idx", "cow><?&(^#1655375120328185000-cow$%#@*&^&dog><?&(^#1655375120328185100-dog$%#@*&^&goat><?&(^#1655404770518678-goat$%#@*&^&hen><?&(^#1655404670510698-hen$%#@*&^&pig><?&(^#1655404770534578-pig$%#@*&^&fish><?&(^#1655403775538278-fish$%#@*&^&"),
+    ("01655375120328185000.cky", "1655375120328185000-cow><?&(^#500 months$%#@*&^&1655375120328185100-dog><?&(^#23 months$%#@*&^&"),
+    ("01655375120328186000.cky", "1655375171402014000-bar><?&(^#foo$%#@*&^&"),
+    ("01655375171402014000.log", "1655404770518678000-goat><?&(^#678 months$%#@*&^&1655404670510698000-hen><?&(^#567 months$%#@*&^&1655404770534578000-pig><?&(^#70 months$%#@*&^&1655403775538278000-fish><?&(^#8990 months$%#@*&^&1655403795838278000-foo><?&(^#890 months$%#@*&^&"),
+    ("delete.del", "1655403795838278000-foo$%#@*&^&1655375171402014000-bar$%#@*&^&"),
+    ("index.idx", "cow><?&(^#1655375120328185000-cow$%#@*&^&dog><?&(^#1655375120328185100-dog$%#@*&^&goat><?&(^#1655404770518678000-goat$%#@*&^&hen><?&(^#1655404670510698000-hen$%#@*&^&pig><?&(^#1655404770534578000-pig$%#@*&^&fish><?&(^#1655403775538278000-fish$%#@*&^&"),
 ];
 
 /// clears the dummy data files in the `db_path` to database
@@ -143,17 +145,152 @@ pub(crate) fn append_to_file<P: AsRef<Path>>(path: P, content: &str) -> io::Resu
     file.write_all(content.as_bytes())
 }
 
-/// Returns the current timestamp as a string.
+/// Runs the I/O operation `op`, retrying it as long as it is interrupted, and, if there is
+/// a retry `policy`, retrying it with exponential backoff when it fails with a transient error
+/// i.e. of kind [WouldBlock] or [TimedOut], up to the policy's maximum number of attempts
 ///
 /// # Errors
 ///
-/// See [std::time::SystemTime::duration_since]
+/// Returns the error of the last attempt of `op` in case it is not retried
+///
+/// [WouldBlock]: std::io::ErrorKind::WouldBlock
+/// [TimedOut]: std::io::ErrorKind::TimedOut
+pub(crate) fn retry_io<T, F: FnMut() -> io::Result<T>>(
+    policy: Option<&RetryPolicy>,
+    mut op: F,
+) -> io::Result<T> {
+    let mut attempts = 1;
+    let mut backoff = policy.map(|p| p.backoff).unwrap_or_default();
+
+    loop {
+        match op() {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                match policy {
+                    Some(policy) if attempts < policy.max_attempts => {
+                        thread::sleep(backoff);
+                        backoff *= 2;
+                        attempts += 1;
+                    }
+                    _ => return Err(err),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns the next timestamp in a strictly increasing sequence given the `previous` timestamp
+/// in the sequence and the `now` timestamp got from the clock.
+///
+/// If the clock returns the same (or an earlier) timestamp twice, the previous timestamp
+/// is incremented by one so that no two timestamps in the sequence are ever equal.
 // #[inline]
-pub(crate) fn get_current_timestamp_str() -> io::Result<String> {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .and_then(|d| Ok(d.as_nanos().to_string()))
-        .or_else(|err| Err(io::Error::new(ErrorKind::Other, err)))
+pub(crate) fn get_next_monotonic_timestamp(previous: u128, now: u128) -> u128 {
+    now.max(previous + 1)
+}
+
+/// Formats the timestamp zero-padded to [TIMESTAMP_WIDTH] digits so that the lexical order
+/// of formatted timestamps is also their chronological order
+// #[inline]
+pub(crate) fn format_timestamp(timestamp: u128) -> String {
+    format!("{:0width$}", timestamp, width = TIMESTAMP_WIDTH)
+}
+
+/// Prefixes the `value` with the [formatted] `timestamp` at which it was modified,
+/// so that it can be saved in the same framing as any other value
+///
+/// [formatted]: format_timestamp
+// #[inline]
+pub(crate) fn encode_value_with_modified_timestamp(value: &str, timestamp: u128) -> String {
+    format!("{}{}", format_timestamp(timestamp), value)
+}
+
+/// Splits a value encoded by [encode_value_with_modified_timestamp] into the timestamp at which
+/// it was modified and the original value. A value without a timestamp prefix is returned as is,
+/// with no timestamp
+// #[inline]
+pub(crate) fn decode_value_with_modified_timestamp(value: &str) -> (Option<u128>, &str) {
+    match value.get(..TIMESTAMP_WIDTH) {
+        Some(prefix) if prefix.bytes().all(|b| b.is_ascii_digit()) => {
+            (prefix.parse().ok(), &value[TIMESTAMP_WIDTH..])
+        }
+        _ => (None, value),
+    }
+}
+
+/// Compares two timestamps, or timestamped keys, or file names without their extensions
+/// by the numeric value of their timestamps, and then by the keys, if any.
+///
+/// Plain string comparison is wrong for timestamps with different numbers of digits
+/// e.g. "999" sorts after "1000" yet it is older. Such timestamps are still found in the keys
+/// of databases created before timestamps were [zero-padded]. Leading zeros are thus ignored.
+///
+/// [zero-padded]: format_timestamp
+// #[inline]
+pub(crate) fn compare_timestamps(a: &str, b: &str) -> Ordering {
+    let (a_timestamp, a_key) = a.split_once('-').unwrap_or((a, ""));
+    let (b_timestamp, b_key) = b.split_once('-').unwrap_or((b, ""));
+    let a_timestamp = a_timestamp.trim_start_matches('0');
+    let b_timestamp = b_timestamp.trim_start_matches('0');
+
+    a_timestamp
+        .len()
+        .cmp(&b_timestamp.len())
+        .then_with(|| a_timestamp.cmp(b_timestamp))
+        .then_with(|| a_key.cmp(b_key))
+}
+
+/// Checks whether `text` matches the glob `pattern`, in which `*` matches any sequence
+/// of characters, `?` matches exactly one character, and `\` escapes the character after it
+/// so that e.g. `\*` matches a literal `*`. A trailing `\` matches itself
+// #[inline]
+pub(crate) fn matches_glob(pattern: &str, text: &str) -> bool {
+    let mut tokens: Vec<(char, bool)> = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => tokens.push((chars.next().unwrap_or('\\'), true)),
+            _ => tokens.push((c, false)),
+        }
+    }
+
+    let text: Vec<char> = text.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    // the position of the last `*` seen and of the text it was matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(('*', false)) => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(('?', false)) => {
+                p += 1;
+                t += 1;
+            }
+            Some((c, _)) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched_up_to)) => {
+                    backtrack = Some((star, matched_up_to + 1));
+                    p = star + 1;
+                    t = matched_up_to + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    tokens[p..].iter().all(|token| *token == ('*', false))
 }
 
 /// Extracts a hashmap of keys and values from a string
@@ -171,7 +308,7 @@ pub(crate) fn extract_key_values_from_str(content: &str) -> io::Result<HashMap<S
     for kv_pair_string in kv_pair_strings {
         let pair: Vec<&str> = kv_pair_string.split(KEY_VALUE_SEPARATOR).collect();
         if pair.len() != 2 {
-            return Err(io::Error::new(ErrorKind::InvalidData, CorruptedDataError));
+            return Err(CorruptedDataError.into());
         }
 
         results.insert(pair[0].to_string(), pair[1].to_string());
@@ -180,10 +317,12 @@ pub(crate) fn extract_key_values_from_str(content: &str) -> io::Result<HashMap<S
     Ok(results)
 }
 
-/// Extracts tokens from a byte array
+/// Extracts tokens from a byte array.
+/// Only the one [crate::constants::TOKEN_SEPARATOR] framing the last token is trimmed off, so a
+/// last token that itself ends with the separator is kept as is, followed by an empty token
 // #[inline]
 pub(crate) fn extract_tokens_from_str(content: &str) -> Vec<String> {
-    let trimmed_content = content.trim_end_matches(TOKEN_SEPARATOR);
+    let trimmed_content = content.strip_suffix(TOKEN_SEPARATOR).unwrap_or(content);
 
     if trimmed_content == "" {
         return vec![];
@@ -195,34 +334,82 @@ pub(crate) fn extract_tokens_from_str(content: &str) -> Vec<String> {
         .collect()
 }
 
+/// Checks whether the given `key` is a well-formed timestamped key i.e. a timestamp of digits
+/// followed by a "-" and a non-empty key, without any key-value or token separators
+// #[inline]
+pub(crate) fn is_timestamped_key(key: &str) -> bool {
+    if key.contains(KEY_VALUE_SEPARATOR) || key.contains(TOKEN_SEPARATOR) {
+        return false;
+    }
+
+    match key.split_once('-') {
+        Some((timestamp, key)) => {
+            !timestamp.is_empty()
+                && timestamp.chars().all(|c| c.is_ascii_digit())
+                && !key.is_empty()
+        }
+        None => false,
+    }
+}
+
+/// Converts the tokens passed into the string to be saved in a file, each token followed by
+/// the [crate::constants::TOKEN_SEPARATOR]. It is the inverse of [extract_tokens_from_str]
+// #[inline]
+pub(crate) fn convert_tokens_to_str<T: AsRef<str>, I: IntoIterator<Item = T>>(tokens: I) -> String {
+    tokens.into_iter().fold("".to_string(), |accum, token| {
+        format!("{}{}{}", accum, token.as_ref(), TOKEN_SEPARATOR)
+    })
+}
+
+/// Overwrites the data in the file at `path` with the given tokens
+///
+/// # Errors
+///
+/// See [fs::write]
+// #[inline]
+pub(crate) fn write_tokens_to_file<P: AsRef<Path>, T: AsRef<str>, I: IntoIterator<Item = T>>(
+    path: P,
+    tokens: I,
+) -> io::Result<()> {
+    fs::write(path, convert_tokens_to_str(tokens))
+}
+
 /// Deletes the key values corresponding to the keysToDelete
 /// if those keys exist in that file
 ///
 /// # Errors
 ///
-/// See [fs::read_to_string] and [fs::write]
+/// See [fs::read_to_string] and [write_tokens_to_file]
 pub(crate) fn delete_key_values_from_file<P: AsRef<Path>>(
     path: P,
     keys_to_delete: &Vec<String>,
 ) -> io::Result<()> {
+    let content = fs::read_to_string(&path)?;
+    write_tokens_to_file(path, filter_out_key_values(&content, keys_to_delete))
+}
+
+/// Returns a copy of the `content` string without the key values corresponding to the keysToDelete
+// #[inline]
+pub(crate) fn delete_key_values_from_str(content: &str, keys_to_delete: &Vec<String>) -> String {
+    convert_tokens_to_str(filter_out_key_values(content, keys_to_delete))
+}
+
+/// Returns the key-value tokens in the `content` string except those corresponding to the keysToDelete
+// #[inline]
+fn filter_out_key_values(content: &str, keys_to_delete: &Vec<String>) -> Vec<String> {
     let keys_to_del_length = keys_to_delete.len();
 
-    let content = fs::read_to_string(&path)?;
-    let kv_pair_strings = extract_tokens_from_str(&content);
+    let kv_pair_strings = extract_tokens_from_str(content);
     let mut prefixes_to_delete: Vec<String> = Vec::with_capacity(keys_to_del_length);
 
     for i in 0..keys_to_del_length {
         prefixes_to_delete.push(format!("{}{}", keys_to_delete[i], KEY_VALUE_SEPARATOR));
     }
 
-    let new_content = kv_pair_strings
+    kv_pair_strings
         .into_iter()
         .filter(|kv| !has_any_of_prefixes(kv, &prefixes_to_delete))
-        .fold("".to_string(), |accum, item| {
-            format!("{}{}{}", accum, item, TOKEN_SEPARATOR)
-        });
-
-    fs::write(path, new_content)
+        .collect()
 }
 
 /// checks if the string phrase has any of the prefixes i.e. starts with any of those prefixes
@@ -237,25 +424,63 @@ fn has_any_of_prefixes(phrase: &str, prefixes: &Vec<String>) -> bool {
     false
 }
 
-/// Overwrites the data in the file at pathToFile with the
-/// equivalent of the map data passed
+/// Overwrites the data in the file at `path` with the
+/// equivalent of the key-value pairs passed
 ///
 /// # Errors
 ///
 /// See [fs::write]
 // #[inline]
-pub(crate) fn persist_map_data_to_file<P: AsRef<Path>>(
-    data: &HashMap<String, String>,
+pub(crate) fn write_key_values_to_file<
+    P: AsRef<Path>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+    I: IntoIterator<Item = (K, V)>,
+>(
     path: P,
+    key_values: I,
 ) -> io::Result<()> {
-    let content = data.into_iter().fold("".to_string(), |accum, (k, v)| {
-        format!(
-            "{}{}{}{}{}",
-            accum, k, KEY_VALUE_SEPARATOR, v, TOKEN_SEPARATOR
-        )
-    });
-
-    fs::write(path, content)
+    fs::write(path, convert_key_values_to_str(key_values))
+}
+
+/// Converts the key-value pairs passed into the string to be saved in a file, each key separated
+/// from its value by the [crate::constants::KEY_VALUE_SEPARATOR].
+/// It is the inverse of [extract_key_values_from_str]
+// #[inline]
+pub(crate) fn convert_key_values_to_str<
+    K: AsRef<str>,
+    V: AsRef<str>,
+    I: IntoIterator<Item = (K, V)>,
+>(
+    key_values: I,
+) -> String {
+    convert_tokens_to_str(
+        key_values
+            .into_iter()
+            .map(|(k, v)| format!("{}{}{}", k.as_ref(), KEY_VALUE_SEPARATOR, v.as_ref())),
+    )
+}
+
+/// Returns the range of timestamps between which the key lies, given the names of the
+/// sorted `data_files` and the `log_file` without their extensions.
+/// It will return None if there is no relevant timestamp range
+// #[inline]
+pub(crate) fn get_timestamp_range_for_key(
+    data_files: &[String],
+    log_file: &str,
+    key: &str,
+) -> Option<(String, String)> {
+    let mut timestamps = data_files.to_vec();
+    timestamps.push(log_file.to_string());
+
+    for i in 1..timestamps.len() {
+        let current = &timestamps[i];
+        if compare_timestamps(current, key).is_gt() {
+            return Some((timestamps[i - 1].clone(), current.clone()));
+        }
+    }
+
+    None
 }
 
 /// Returns the size of the file at the given `path` in kilobytes
@@ -268,3 +493,222 @@ pub(crate) fn get_file_size<P: AsRef<Path>>(path: P) -> io::Result<f64> {
     let file_size_in_bytes = fs::metadata(path)?.len();
     Ok(file_size_in_bytes as f64 / 1024.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::options::RetryPolicy;
+    use crate::utils;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::{self, ErrorKind};
+    use std::path::Path;
+    use std::time::Duration;
+
+    const DB_PATH: &str = "test_utils_db";
+
+    #[test]
+    fn compare_timestamps_orders_by_numeric_value_then_key() {
+        let mut timestamps = vec!["1000-b", "999", "1000", "10000-a", "1000-a", "999-z"];
+        timestamps.sort_by(|a, b| utils::compare_timestamps(a, b));
+
+        assert_eq!(
+            vec!["999", "999-z", "1000", "1000-a", "1000-b", "10000-a"],
+            timestamps
+        );
+    }
+
+    #[test]
+    fn matches_glob_supports_wildcards_and_escapes() {
+        let cases = [
+            ("user:*:active", "user:42:active", true),
+            ("user:*:active", "user::active", true),
+            ("user:*:active", "user:42:inactive", false),
+            ("user:*:active", "user:42:active:yes", false),
+            ("*:*:?", "a:b:c", true),
+            ("*:*:?", "a:b:", false),
+            ("h?n", "hen", true),
+            ("h?n", "hn", false),
+            ("*a*b*", "xxaxxbxx", true),
+            ("*a*b*", "xxbxxaxx", false),
+            ("*", "", true),
+            ("", "", true),
+            ("", "a", false),
+            ("price\\*", "price*", true),
+            ("price\\*", "price10", false),
+            ("what\\?", "what?", true),
+            ("what\\?", "whats", false),
+            ("back\\\\slash", "back\\slash", true),
+            ("trailing\\", "trailing\\", true),
+        ];
+
+        for (pattern, text, expected) in cases {
+            assert_eq!(
+                expected,
+                utils::matches_glob(pattern, text),
+                "pattern: {}, text: {}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn decode_value_with_modified_timestamp_is_inverse_of_encode() {
+        for value in ["", "500 months", "01234567890123456789"] {
+            let encoded = utils::encode_value_with_modified_timestamp(value, 1655375120328185000);
+
+            assert_eq!(
+                (Some(1655375120328185000), value),
+                utils::decode_value_with_modified_timestamp(&encoded)
+            );
+        }
+
+        assert_eq!(
+            (None, "500 months"),
+            utils::decode_value_with_modified_timestamp("500 months")
+        );
+    }
+
+    #[test]
+    fn convert_tokens_to_str_is_inverse_of_extract_tokens_from_str() {
+        let tokens = vec!["1655403795838278000-foo", "1655375171402014000-bar"];
+        let content = utils::convert_tokens_to_str(&tokens);
+
+        assert_eq!(
+            "1655403795838278000-foo$%#@*&^&1655375171402014000-bar$%#@*&^&",
+            content
+        );
+        assert_eq!(tokens, utils::extract_tokens_from_str(&content));
+        assert_eq!("", utils::convert_tokens_to_str(Vec::<String>::new()));
+    }
+
+    #[test]
+    fn extract_tokens_from_str_trims_only_the_last_token_separator() {
+        assert_eq!(
+            vec!["a".to_string(), "".to_string()],
+            utils::extract_tokens_from_str("a$%#@*&^&$%#@*&^&")
+        );
+        assert_eq!(
+            vec!["a".to_string()],
+            utils::extract_tokens_from_str("a$%#@*&^&")
+        );
+        assert_eq!(vec!["a".to_string()], utils::extract_tokens_from_str("a"));
+        assert_eq!(Vec::<String>::new(), utils::extract_tokens_from_str(""));
+        assert_eq!(
+            Vec::<String>::new(),
+            utils::extract_tokens_from_str("$%#@*&^&")
+        );
+    }
+
+    #[test]
+    fn extract_key_values_from_str_rejects_rather_than_truncates_value_ending_with_separator() {
+        let content = "k><?&(^#v$%#@*&^&$%#@*&^&";
+
+        assert!(utils::extract_key_values_from_str(content).is_err());
+    }
+
+    #[test]
+    fn convert_key_values_to_str_is_inverse_of_extract_key_values_from_str() {
+        let key_values: HashMap<String, String> = HashMap::from([
+            ("cow".to_string(), "1655375120328185000-cow".to_string()),
+            ("dog".to_string(), "1655375120328185100-dog".to_string()),
+        ]);
+        let content = utils::convert_key_values_to_str(&key_values);
+
+        assert_eq!(
+            key_values,
+            utils::extract_key_values_from_str(&content).unwrap()
+        );
+        assert_eq!(
+            "cow><?&(^#1655375120328185000-cow$%#@*&^&",
+            utils::convert_key_values_to_str([("cow", "1655375120328185000-cow")])
+        );
+    }
+
+    #[test]
+    fn write_tokens_and_key_values_to_file_round_trip() {
+        let db_path = Path::new(DB_PATH);
+        let del_file_path = db_path.join("delete.del");
+        let index_file_path = db_path.join("index.idx");
+        let tokens = vec!["1655403795838278000-foo".to_string()];
+        let key_values: HashMap<String, String> =
+            HashMap::from([("cow".to_string(), "1655375120328185000-cow".to_string())]);
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+        fs::create_dir_all(db_path).expect("creates db folder");
+        utils::write_tokens_to_file(&del_file_path, &tokens).expect("writes tokens");
+        utils::write_key_values_to_file(&index_file_path, &key_values).expect("writes key values");
+
+        let del_file_content = fs::read_to_string(&del_file_path).expect("reads del file");
+        let index_file_content = fs::read_to_string(&index_file_path).expect("reads index file");
+
+        assert_eq!(tokens, utils::extract_tokens_from_str(&del_file_content));
+        assert_eq!(
+            key_values,
+            utils::extract_key_values_from_str(&index_file_content).unwrap()
+        );
+
+        utils::clear_dummy_file_data_in_db(DB_PATH).expect("clears dummy data in db");
+    }
+
+    #[test]
+    fn retry_io_always_retries_interrupted_operations() {
+        let mut fake_io = FakeIo::new(ErrorKind::Interrupted, 3);
+
+        assert_eq!(4, utils::retry_io(None, || fake_io.write()).unwrap());
+    }
+
+    #[test]
+    fn retry_io_retries_transient_errors_only_as_allowed_by_policy() {
+        let policy = RetryPolicy::new(4, Duration::from_millis(1));
+
+        for kind in [ErrorKind::WouldBlock, ErrorKind::TimedOut] {
+            let mut fake_io = FakeIo::new(kind, 3);
+            assert_eq!(
+                4,
+                utils::retry_io(Some(&policy), || fake_io.write()).unwrap()
+            );
+
+            let mut fake_io = FakeIo::new(kind, 4);
+            let err = utils::retry_io(Some(&policy), || fake_io.write()).unwrap_err();
+            assert_eq!(kind, err.kind());
+            assert_eq!(4, fake_io.attempts);
+
+            let mut fake_io = FakeIo::new(kind, 1);
+            assert!(utils::retry_io(None, || fake_io.write()).is_err());
+            assert_eq!(1, fake_io.attempts);
+        }
+
+        let mut fake_io = FakeIo::new(ErrorKind::PermissionDenied, 1);
+        assert!(utils::retry_io(Some(&policy), || fake_io.write()).is_err());
+        assert_eq!(1, fake_io.attempts);
+    }
+
+    /// A fake I/O layer whose writes fail with errors of the given `kind` the first
+    /// `failures` times, and then succeed
+    struct FakeIo {
+        kind: ErrorKind,
+        failures: u32,
+        attempts: u32,
+    }
+
+    impl FakeIo {
+        fn new(kind: ErrorKind, failures: u32) -> FakeIo {
+            FakeIo {
+                kind,
+                failures,
+                attempts: 0,
+            }
+        }
+
+        /// Returns the number of attempts made so far if the write succeeds
+        fn write(&mut self) -> io::Result<u32> {
+            self.attempts += 1;
+            if self.attempts <= self.failures {
+                return Err(io::Error::from(self.kind));
+            }
+
+            Ok(self.attempts)
+        }
+    }
+}